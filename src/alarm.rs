@@ -16,11 +16,16 @@
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
 use crate::clock::Clock;
-use crate::consts::{COLOR, LABEL_SIZE_LIMIT};
+use crate::consts::LABEL_SIZE_LIMIT;
+use crate::events::{Queue, Value};
+use crate::lap::Laps;
 use crate::layout::{Layout, Position};
+use crate::palette::Palette;
 use crate::utils::*;
+use std::convert::TryFrom;
 use std::io::BufRead;
 use std::io::Write;
+use std::time::{self, SystemTime, UNIX_EPOCH};
 use termion::raw::RawTerminal;
 use termion::{color, cursor, style};
 use unicode_width::UnicodeWidthStr;
@@ -28,6 +33,184 @@ use unicode_width::UnicodeWidthStr;
 // Delimiter between time and label. Remember to update usage information in
 // consts.rs when changing this.
 const DELIMITER: char = '/';
+// Prefix of alarms set to a time of day.
+const TIME_OF_DAY: char = '@';
+// Prefixes of repeating alarms.
+const REPEAT: char = '*';
+const REPEAT_WORD: &str = "every ";
+
+// Parse time string into seconds. Accepts colon separated segments
+// (HH:MM:SS) with an optional day prefix (e.g. "2d04:00:00") as well as
+// numbers with unit suffixes, e.g. "1h30m", "2.5m" or "90s".
+pub fn parse_time(input: &str) -> Result<u32, &'static str> {
+    if !input.contains(':') {
+        return parse_units(input);
+    }
+    match input.split_once(['d', 'D']) {
+        Some((days, segments)) => {
+            let days = match days.trim().parse::<u32>() {
+                Ok(days) => days,
+                Err(_) => return Err("Could not parse number of days."),
+            };
+            let time = parse_segments(segments)?;
+            days.checked_mul(24 * 60 * 60)
+                .and_then(|days| days.checked_add(time))
+                .ok_or("Value too large.")
+        }
+        None => parse_segments(input),
+    }
+}
+
+// Parse colon separated segments (HH:MM:SS) into seconds.
+fn parse_segments(input: &str) -> Result<u32, &'static str> {
+    let mut time: u32 = 0;
+
+    for (i, sub) in input.rsplit(':').enumerate() {
+        match sub.trim().parse::<u32>() {
+            // Too many segments.
+            Ok(_) if i > 2 => return Err("Too many segments to parse as time."),
+            // Valid.
+            Ok(d) => {
+                time = d
+                    .checked_mul(60u32.pow(i as u32))
+                    .and_then(|d| time.checked_add(d))
+                    .ok_or("Value too large.")?;
+            }
+            // Ignore failure caused by an empty string.
+            // TODO: Match error kind when stable. See documentation
+            // for std::num::ParseIntError and
+            // https://github.com/rust-lang/rust/issues/22639
+            Err(_) if sub.trim().is_empty() => (),
+            Err(_) if sub.contains('.') => {
+                return Err("Fractions require units, e.g. \"2.5m\".")
+            }
+            Err(_) if sub.contains(char::is_alphabetic) => {
+                return Err("Mixing units and colons is ambiguous. Use either.")
+            }
+            // Could not parse to u32.
+            Err(_) => return Err("Could not parse value as integer."),
+        }
+    }
+    Ok(time)
+}
+
+// Parse time of day (HH:MM or HH:MM:SS) into seconds since midnight.
+fn parse_time_of_day(input: &str) -> Result<u32, &'static str> {
+    let mut time: u32 = 0;
+
+    for (i, sub) in input.split(':').enumerate() {
+        if i > 2 {
+            return Err("Too many segments to parse as time of day.");
+        }
+        let value = match sub.trim().parse::<u32>() {
+            Ok(value) => value,
+            Err(_) => return Err("Could not parse time of day. Use HH:MM."),
+        };
+        if value >= if i == 0 { 24 } else { 60 } {
+            return Err("Not a valid time of day.");
+        }
+        time += value * 60u32.pow(2 - i as u32);
+    }
+    Ok(time)
+}
+
+// Parse interval of repeating alarms with optional limits, e.g.
+// "10m x3" or "10:00 until 1h".
+fn parse_repeat(input: &str) -> Result<Repeat, &'static str> {
+    let (input, until) = match input.split_once("until") {
+        Some((input, until)) => (input, Some(parse_time(until.trim())?)),
+        None => (input, None),
+    };
+    let (input, limit) = match input.trim().rsplit_once(['x', 'X']) {
+        Some((input, limit)) => match limit.trim().parse::<u32>() {
+            Ok(limit) => (input, Some(limit)),
+            Err(_) => return Err("Could not parse number of repetitions."),
+        },
+        None => (input, None),
+    };
+    let interval = parse_time(input.trim())?;
+
+    if interval == 0 {
+        return Err("Interval evaluates to zero.");
+    }
+    if limit == Some(0) {
+        return Err("Number of repetitions evaluates to zero.");
+    }
+    if until.is_some_and(|until| until < interval) {
+        return Err("Ends before the first repetition.");
+    }
+    Ok(Repeat {
+        interval,
+        count: 0,
+        limit,
+        until,
+    })
+}
+
+// Parse numbers with unit suffixes, e.g. "1h 30m" or "2.5m", into seconds.
+// A single number without unit is read as seconds.
+fn parse_units(input: &str) -> Result<u32, &'static str> {
+    let mut chars = input.trim().chars().peekable();
+    let mut time: u64 = 0;
+    // Last unit in seconds. Units have to be given in descending order.
+    let mut last_unit: Option<u64> = None;
+
+    while chars.peek().is_some() {
+        let mut number = String::new();
+        let mut unit = String::new();
+
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+            number.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        while let Some(c) = chars.next_if(|c| c.is_alphabetic()) {
+            unit.push(c);
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        if number.is_empty() {
+            return Err("Expected a number in front of every unit.");
+        }
+        let unit = match unit.to_lowercase().as_str() {
+            "" if last_unit.is_none() && chars.peek().is_none() => 1,
+            "" => return Err("Number without unit is ambiguous. Add h, m or s."),
+            "d" | "day" | "days" => 24 * 60 * 60,
+            "h" | "hr" | "hrs" | "hour" | "hours" => 60 * 60,
+            "m" | "min" | "mins" | "minute" | "minutes" => 60,
+            "s" | "sec" | "secs" | "second" | "seconds" => 1,
+            _ => return Err("Unknown unit. Use d, h, m or s."),
+        };
+        if last_unit.is_some_and(|last| unit >= last) {
+            return Err("Units have to be given once each, in descending order.");
+        }
+        last_unit = Some(unit);
+
+        // Split into integral and fractional part.
+        let (integral, fraction) = match number.split_once('.') {
+            Some((_, fraction)) if fraction.contains('.') => {
+                return Err("Could not parse value as number.")
+            }
+            Some((integral, fraction)) => (integral, fraction),
+            None => (number.as_str(), ""),
+        };
+        if integral.is_empty() && fraction.is_empty() {
+            return Err("Could not parse value as number.");
+        }
+        // Calculate with fixed point numbers to keep exact results.
+        let scale = 10u64
+            .checked_pow(fraction.len() as u32)
+            .ok_or("Value too large.")?;
+        let value = format!("{}{}", integral, fraction)
+            .parse::<u64>()
+            .map_err(|_| "Value too large.")?;
+        let secs = value.checked_mul(unit).ok_or("Value too large.")?;
+        if secs % scale != 0 {
+            return Err("Evaluates to fractions of a second.");
+        }
+        time = time.checked_add(secs / scale).ok_or("Value too large.")?;
+    }
+    u32::try_from(time).map_err(|_| "Value too large.")
+}
 
 pub struct Countdown {
     pub value: u32,
@@ -61,31 +244,20 @@ impl Countdown {
         stdout: &mut RawTerminal<W>
     ) -> Result<(), std::io::Error> {
         if let Some(pos) = &self.position {
-            if self.value < 3600 {
-                // Show minutes and seconds.
-                write!(
-                    stdout,
-                    "{}(-{:02}:{:02})",
-                    cursor::Goto(pos.col, pos.line),
-                    (self.value / 60) % 60,
-                    self.value % 60
-                )?;
-                if self.value == 3599 {
-                    // Write three additional spaces after switching from hour display to
-                    // minute display.
-                    write!(stdout, "   ")?;
-                }
-            } else {
-                // Show hours, minutes and seconds.
-                write!(
-                    stdout,
-                    "{}(-{:02}:{:02}:{:02})",
-                    cursor::Goto(pos.col, pos.line),
-                    self.value / 3600,
-                    (self.value / 60) % 60,
-                    self.value % 60
-                )?;
-            }
+            let text = format_duration(self.value);
+            // Write additional spaces if the countdown just got shorter,
+            // e.g. after switching from hour display to minute display.
+            let padding = format_duration(self.value.saturating_add(1))
+                .len()
+                .saturating_sub(text.len());
+            write!(
+                stdout,
+                "{}(-{}){:padding$}",
+                cursor::Goto(pos.col, pos.line),
+                text,
+                "",
+                padding = padding,
+            )?;
         }
         Ok(())
     }
@@ -98,7 +270,7 @@ impl Countdown {
         offset: usize,
         index: usize
     ) {
-        let mut col = layout.roster.col + 3 + UnicodeWidthStr::width(alarm.label.as_str()) as u16;
+        let mut col = layout.roster.col + 3 + alarm.width();
         let mut line = layout.roster.line + index as u16;
 
         // Compensate for "hidden" items in the alarm roster.
@@ -113,7 +285,7 @@ impl Countdown {
                 line -= offset as u16;
             }
         }
-        if line > layout.roster_height.saturating_add(2) {
+        if line >= layout.roster.line + layout.roster_height {
             // Draw next to lower placeholder.
             line = layout.roster.line + layout.roster_height;
             col = layout.roster.col + 6;
@@ -122,23 +294,359 @@ impl Countdown {
     }
 }
 
+#[derive(Clone)]
 pub struct Alarm {
     pub time: u32,
     pub label: String,
     color_index: usize,
     exceeded: bool,
+    // Set for alarms given as time of day.
+    wall: Option<WallTime>,
+    // Set for repeating alarms.
+    repeat: Option<Repeat>,
+    // Raised, but not acknowledged yet.
+    pending: bool,
+    // Last notification of pending alarms.
+    notified: Option<time::Instant>,
+    // Set for one-off alarms created by snoozing.
+    snoozed: bool,
+    // Set for alarms ending a step of a sequence. Duration of the step.
+    step: Option<u32>,
+    // Selected in the roster.
+    selected: bool,
+}
+
+// Alarm set to a time of day. "Alarm.time" is derived from "due" on every
+// check, so these alarms go off at the given time no matter if the clock was
+// paused, shifted or reset.
+#[derive(Clone)]
+struct WallTime {
+    due: SystemTime,
+    // Seconds since midnight.
+    time_of_day: u32,
+}
+
+// Alarm repeating at a fixed interval. "Alarm.time" holds the next
+// occurrence.
+#[derive(Clone)]
+struct Repeat {
+    interval: u32,
+    // Number of occurrences passed.
+    count: u32,
+    // Maximum number of occurrences.
+    limit: Option<u32>,
+    // Time of the last possible occurrence.
+    until: Option<u32>,
+}
+
+impl Repeat {
+    // Number of occurrences up to the given time.
+    fn passed(&self, time: u32) -> u32 {
+        let mut passed = time / self.interval;
+        if let Some(limit) = self.limit {
+            passed = passed.min(limit);
+        }
+        if let Some(until) = self.until {
+            passed = passed.min(until / self.interval);
+        }
+        passed
+    }
+
+    // Time of the next occurrence, if any.
+    fn next(&self) -> Option<u32> {
+        let next = self.count + 1;
+        if self.passed(u32::MAX) < next {
+            return None;
+        }
+        self.interval.checked_mul(next)
+    }
+
+    // Number of occurrences left, if limited.
+    fn remaining(&self) -> Option<u32> {
+        if self.limit.is_some() || self.until.is_some() {
+            Some(self.passed(u32::MAX) - self.count)
+        } else {
+            None
+        }
+    }
+}
+
+// Alarm parsed from user input, not added to a roster yet.
+struct Spec {
+    label: String,
+    time: u32,
+    wall: Option<WallTime>,
+    repeat: Option<Repeat>,
+}
+
+impl Spec {
+    // Parse alarm from string.
+    fn parse(input: &str) -> Result<Spec, &'static str> {
+        let mut label: String;
+        let time_str: &str;
+
+        if let Some(i) = input.find(DELIMITER) {
+            label = input[(i + 1)..].to_string();
+            // Truncate label.
+            grapheme_truncate(&mut label, LABEL_SIZE_LIMIT, '…');
+            time_str = input[..i].trim();
+        } else {
+            label = input.to_string();
+            time_str = input.trim();
+        }
+
+        // Alarm set to a time of day.
+        if let Some(time_str) = time_str.strip_prefix(TIME_OF_DAY) {
+            let time_of_day = parse_time_of_day(time_str)?;
+            if input.find(DELIMITER).is_none() {
+                // Normalize label.
+                label = format!("{}{}", TIME_OF_DAY, format_time_of_day(time_of_day));
+            }
+            // Find next occurrence of the given time of day.
+            let now = SystemTime::now();
+            let day = 24 * 60 * 60;
+            let time = match (time_of_day + day - local_time_of_day(now)) % day {
+                0 => day,
+                time => time,
+            };
+            let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+            let due = UNIX_EPOCH + time::Duration::from_secs(secs + time as u64);
+            // "time" is relative to the current time. It will be corrected
+            // on the next check.
+            return Ok(Spec {
+                label,
+                time,
+                wall: Some(WallTime { due, time_of_day }),
+                repeat: None,
+            });
+        }
+
+        // Repeating alarm.
+        let repeat_str = time_str.strip_prefix(REPEAT).or_else(|| {
+            time_str
+                .get(..REPEAT_WORD.len())
+                .filter(|prefix| prefix.eq_ignore_ascii_case(REPEAT_WORD))
+                .map(|_| &time_str[REPEAT_WORD.len()..])
+        });
+        if let Some(repeat_str) = repeat_str {
+            let repeat = parse_repeat(repeat_str)?;
+            if input.find(DELIMITER).is_none() {
+                // Normalize label.
+                label = format!("{}{}", REPEAT, format_duration(repeat.interval));
+            }
+            let time = repeat.interval;
+            return Ok(Spec {
+                label,
+                time,
+                wall: None,
+                repeat: Some(repeat),
+            });
+        }
+
+        let time = parse_time(time_str)?;
+
+        // Skip if time is out of boundaries.
+        if time == 0 {
+            return Err("Evaluates to zero.");
+        };
+        Ok(Spec {
+            label,
+            time,
+            wall: None,
+            repeat: None,
+        })
+    }
+
+    // Check whether the given alarm goes off at the same time.
+    fn duplicates(&self, alarm: &Alarm) -> bool {
+        self.same_time(alarm.time, alarm.wall.as_ref(), alarm.repeat.as_ref())
+    }
+
+    fn same_time(&self, time: u32, wall: Option<&WallTime>, repeat: Option<&Repeat>) -> bool {
+        match (&self.wall, &self.repeat) {
+            (Some(own), _) => wall.map(|w| w.time_of_day) == Some(own.time_of_day),
+            (_, Some(own)) => repeat.map(|r| r.interval) == Some(own.interval),
+            _ => wall.is_none() && repeat.is_none() && time == self.time,
+        }
+    }
+}
+
+// Check whether "input" is a valid alarm, without adding it anywhere.
+pub fn validate(input: &str) -> Result<(), &'static str> {
+    Spec::parse(input).map(|_| ())
 }
 
 impl Alarm {
-    fn reset(&mut self) {
-        self.exceeded = false;
+    // Update state according to the given clock time. Used when time jumps.
+    fn rewind(&mut self, time: u32) {
+        self.pending = false;
+        match &mut self.repeat {
+            Some(repeat) => {
+                repeat.count = repeat.passed(time);
+                match repeat.next() {
+                    Some(next) => {
+                        self.time = next;
+                        self.exceeded = false;
+                    }
+                    None => self.exceeded = true,
+                }
+            }
+            None => {
+                // Alarms set to a time of day are not affected.
+                if self.wall.is_none() {
+                    self.exceeded = self.time <= time;
+                }
+            }
+        }
+    }
+
+    // Raise alarm. Repeating alarms are scheduled to their next occurrence.
+    fn raise(&mut self, time: u32) {
+        if self.repeat.is_some() {
+            self.rewind(time);
+        } else {
+            self.exceeded = true;
+        }
+        self.pending = true;
+        self.notified = Some(time::Instant::now());
+    }
+
+    // Returns true if the alarm went off at least once.
+    fn has_passed(&self) -> bool {
+        self.exceeded || self.repeat.as_ref().is_some_and(|r| r.count > 0)
+    }
+
+    pub fn is_time_of_day(&self) -> bool {
+        self.wall.is_some()
+    }
+
+    // Alarm time as given by the user. Seconds since midnight for alarms set
+    // to a time of day, duration of the step for steps of a sequence.
+    pub fn nominal_time(&self) -> u32 {
+        match (&self.wall, self.step) {
+            (Some(wall), _) => wall.time_of_day,
+            (None, Some(step)) => step,
+            (None, None) => self.time,
+        }
+    }
+
+    // Alarm time in a format suitable for editing.
+    pub fn spec(&self) -> String {
+        if let Some(wall) = &self.wall {
+            return format!("{}{}", TIME_OF_DAY, format_time_of_day(wall.time_of_day));
+        }
+        match &self.repeat {
+            Some(repeat) => {
+                let mut spec = format!("{}{}", REPEAT, format_duration(repeat.interval));
+                if let Some(limit) = repeat.limit {
+                    spec.push_str(&format!(" x{}", limit));
+                }
+                if let Some(until) = repeat.until {
+                    spec.push_str(&format!(" until {}", format_duration(until)));
+                }
+                spec
+            }
+            None => format_duration(self.time),
+        }
+    }
+
+    // Additional information shown next to the label.
+    fn note(&self) -> Option<String> {
+        let mut notes = Vec::new();
+
+        if let Some(wall) = &self.wall {
+            notes.push(format!("{}{}", TIME_OF_DAY, format_time_of_day(wall.time_of_day)));
+        }
+        if let Some(repeat) = &self.repeat {
+            notes.push(format!("{}{}", REPEAT, format_duration(repeat.interval)));
+            if !self.exceeded {
+                notes.push(format!("next {}", format_duration(self.time)));
+            }
+            if let Some(remaining) = repeat.remaining() {
+                notes.push(format!("{} left", remaining));
+            }
+        }
+        if self.snoozed {
+            notes.push(String::from("zZ"));
+        }
+        // Skip what the label already says.
+        notes.retain(|note| *note != self.label);
+
+        if notes.is_empty() {
+            None
+        } else {
+            Some(notes.join(" "))
+        }
+    }
+
+    // State as written to the session file: flags, time and spec with label.
+    // Time holds the due date in UNIX time for alarms set to a time of day.
+    fn state(&self) -> String {
+        let mut flags = String::new();
+        if self.exceeded {
+            flags.push('x');
+        }
+        if self.pending {
+            flags.push('p');
+        }
+        if self.snoozed {
+            flags.push('z');
+        }
+        // Alarms of steps keep the length of the step.
+        let spec = match self.step {
+            Some(step) => {
+                flags.push('s');
+                format_duration(step)
+            }
+            None => self.spec(),
+        };
+        if flags.is_empty() {
+            flags.push('-');
+        }
+        let time = match &self.wall {
+            Some(wall) => wall.due.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs()),
+            None => self.time as u64,
+        };
+        format!("{} {} {}{}{}", flags, time, spec, DELIMITER, self.label)
+    }
+
+    // Write event concerning this alarm.
+    fn emit(&self, events: &mut Queue, event: &'static str) {
+        events.push(
+            event,
+            &[
+                ("label", Value::Str(&self.label)),
+                ("spec", Value::Str(&self.spec())),
+                ("alarm_time", Value::Num(self.time as i64)),
+            ],
+        );
+    }
+
+    // Width of label and note.
+    fn width(&self) -> u16 {
+        let width = UnicodeWidthStr::width(self.label.as_str()) as u16;
+        match self.note() {
+            Some(note) => width + 1 + note.len() as u16,
+            None => width,
+        }
     }
 }
 
+#[derive(Clone)]
 pub struct AlarmRoster {
     list: Vec<Alarm>,
     offset: usize,
     hints_shown: bool,
+    // Laps are listed below the alarms.
+    pub laps: Laps,
+    // Events not written yet.
+    pub events: Queue,
+}
+
+impl Default for AlarmRoster {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl AlarmRoster {
@@ -149,75 +657,247 @@ impl AlarmRoster {
             offset: 0,
             // Scrolling hint.
             hints_shown: false,
+            laps: Laps::new(),
+            events: Queue::new(),
         }
     }
 
-    // Parse string and add as alarm.
-    pub fn add(&mut self, input: &String) -> Result<(), &'static str> {
-        let mut time: u32 = 0;
-        let mut label: String;
-        let time_str: &str;
+    // Parse string and add as alarm. Returns the alarm time in seconds.
+    pub fn add(&mut self, input: &str) -> Result<u32, &'static str> {
+        let index = self.parse(input)?;
+        self.list[index].emit(&mut self.events, "alarm_added");
+        Ok(self.list[index].time)
+    }
 
-        if let Some(i) = input.find(DELIMITER) {
-            label = input[(i + 1)..].to_string();
-            // Truncate label.
-            grapheme_truncate(&mut label, LABEL_SIZE_LIMIT, '…');
-            time_str = &input[..i].trim();
-        } else {
-            label = input.clone();
-            time_str = &input.trim();
-        }
-
-        // Parse input into seconds.
-        for (i, sub) in time_str.rsplit(':').enumerate() {
-            match sub.parse::<u32>() {
-                // Too many segments.
-                Ok(_) if i > 2 => return Err("Too many segments to parse as time."),
-                // Valid.
-                Ok(d) => time += d * 60u32.pow(i as u32),
-                // Ignore failure caused by an empty string.
-                // TODO: Match error kind when stable. See documentation
-                // for std::num::ParseIntError and
-                // https://github.com/rust-lang/rust/issues/22639
-                Err(_) if sub.is_empty() => (),
-                // Could not parse to u32.
-                Err(_) => return Err("Could not parse value as integer."),
+    // Add all alarms of a recipe. Adds none if any of them fails to parse.
+    pub fn add_recipe(&mut self, alarms: &[String]) -> Result<(), &'static str> {
+        if alarms.is_empty() {
+            return Err("Recipe has no alarms.");
+        }
+        let mut specs: Vec<Spec> = Vec::with_capacity(alarms.len());
+        for input in alarms {
+            let spec = Spec::parse(input)?;
+            if self.list.iter().any(|a| spec.duplicates(a))
+                || specs
+                    .iter()
+                    .any(|s| spec.same_time(s.time, s.wall.as_ref(), s.repeat.as_ref()))
+            {
+                return Err("Already exists. Duplicate entries not supported.");
             }
+            specs.push(spec);
+        }
+        for spec in specs {
+            let index = self.insert(spec.label, spec.time, spec.wall, spec.repeat);
+            self.list[index].emit(&mut self.events, "alarm_added");
         }
+        Ok(())
+    }
 
-        // Skip if time is out of boundaries.
-        if time == 0 {
-            return Err("Evaluates to zero.");
-        };
-        if time >= 24 * 60 * 60 {
-            return Err("Values >24h not supported.");
-        };
+    // Parse string and add as alarm. Returns the index of the new alarm.
+    fn parse(&mut self, input: &str) -> Result<usize, &'static str> {
+        let spec = Spec::parse(input)?;
         // Filter out duplicate entries.
-        if self.list.iter().any(|a| a.time == time) {
+        if self.list.iter().any(|a| spec.duplicates(a)) {
             return Err("Already exists. Duplicate entries not supported.");
         }
+        Ok(self.insert(spec.label, spec.time, spec.wall, spec.repeat))
+    }
 
-        // Label will never change from now on.
+    fn insert(
+        &mut self,
+        mut label: String,
+        time: u32,
+        wall: Option<WallTime>,
+        repeat: Option<Repeat>,
+    ) -> usize {
         label.shrink_to_fit();
         let alarm = Alarm {
             label,
             time,
-            color_index: (self.list.len() % COLOR.len()),
+            color_index: self.list.len(),
             exceeded: false,
+            wall,
+            repeat,
+            pending: false,
+            notified: None,
+            snoozed: false,
+            step: None,
+            selected: false,
         };
 
         // Add to list, insert based on alarm time.
         if let Some(i) = self.list.iter().position(|a| a.time > time) {
             self.list.insert(i, alarm);
+            i
         } else {
             self.list.push(alarm);
+            self.list.len() - 1
         }
+    }
+
+    // Return time of the last alarm.
+    pub fn last_time(&self) -> Option<u32> {
+        self.list.last().map(|a| a.time)
+    }
+
+    // Return the alarm to go off next.
+    pub fn next(&self) -> Option<&Alarm> {
+        self.list.iter().find(|a| !a.exceeded)
+    }
+
+    // Alarms yet to go off.
+    pub fn upcoming(&self) -> impl Iterator<Item = &Alarm> {
+        self.list.iter().filter(|a| !a.exceeded)
+    }
+
+    // Remove first alarm matching the given label or time.
+    pub fn remove(&mut self, name: &str) -> Option<Alarm> {
+        let time = parse_time(name).ok();
+        let index = self.list.iter().position(|a| {
+            a.label == name
+                || a.spec() == name
+                || a.wall.is_none() && a.repeat.is_none() && Some(a.time) == time
+        })?;
+        let alarm = self.list.remove(index);
+        alarm.emit(&mut self.events, "alarm_removed");
+        Some(alarm)
+    }
+
+    // Remove selected alarm or the last one if none is selected. Selects the
+    // following alarm.
+    pub fn delete(&mut self, layout: &Layout) -> Option<Alarm> {
+        let alarm = match self.selected() {
+            Some(index) => {
+                let alarm = self.list.remove(index);
+                let next = index.min(self.list.len().saturating_sub(1));
+                if let Some(alarm) = self.list.get_mut(next) {
+                    alarm.selected = true;
+                }
+                self.scroll_to_selection(layout);
+                Some(alarm)
+            }
+            None => self.list.pop(),
+        };
+        if let Some(alarm) = &alarm {
+            alarm.emit(&mut self.events, "alarm_removed");
+        }
+        alarm
+    }
+
+    // Return index of selected alarm.
+    pub fn selected(&self) -> Option<usize> {
+        self.list.iter().position(|a| a.selected)
+    }
+
+    // Return selected alarm.
+    pub fn selection(&self) -> Option<&Alarm> {
+        self.list.iter().find(|a| a.selected)
+    }
+
+    // Move selection up or down. Selects the first or last alarm if none is
+    // selected.
+    pub fn select(&mut self, layout: &Layout, down: bool) -> bool {
+        if self.list.is_empty() {
+            return false;
+        }
+        let last = self.list.len() - 1;
+        let index = match self.selected() {
+            Some(index) if down => (index + 1).min(last),
+            Some(index) => index.saturating_sub(1),
+            None if down => 0,
+            None => last,
+        };
+        self.unselect();
+        self.list[index].selected = true;
+        self.scroll_to_selection(layout);
+        true
+    }
+
+    // Clear selection. Returns false if nothing was selected.
+    pub fn unselect(&mut self) -> bool {
+        let selected = self.selected().is_some();
+        for alarm in self.list.iter_mut() {
+            alarm.selected = false;
+        }
+        selected
+    }
+
+    // Replace selected alarm by a new one parsed from "input".
+    pub fn edit(&mut self, input: &str) -> Result<(), &'static str> {
+        let index = self.selected().ok_or("No alarm selected.")?;
+        let old = self.list.remove(index);
+
+        match self.parse(input) {
+            Ok(new) => {
+                let alarm = &mut self.list[new];
+                alarm.color_index = old.color_index;
+                alarm.selected = true;
+                // Keep state not expressed by the spec.
+                alarm.pending = old.pending;
+                alarm.notified = old.notified;
+                alarm.snoozed = old.snoozed;
+                match (&mut alarm.repeat, &old.repeat) {
+                    // Skip occurrences passed already.
+                    (Some(repeat), Some(passed)) => {
+                        repeat.count = passed.count.min(repeat.passed(u32::MAX));
+                        match repeat.next() {
+                            Some(next) => alarm.time = next,
+                            None => alarm.exceeded = true,
+                        }
+                    }
+                    // A step alarm moved elsewhere no longer ends the step.
+                    (None, None) if alarm.wall.is_none() && alarm.time == old.time => {
+                        alarm.exceeded = old.exceeded;
+                        alarm.step = old.step;
+                    }
+                    _ => (),
+                }
+                old.emit(&mut self.events, "alarm_removed");
+                alarm.emit(&mut self.events, "alarm_added");
+                self.list.sort_by_key(|a| a.time);
+                Ok(())
+            }
+            Err(error) => {
+                // Put back original.
+                self.list.insert(index, old);
+                Err(error)
+            }
+        }
+    }
+
+    // Change label of selected alarm. Falls back to the alarm time if empty.
+    pub fn relabel(&mut self, label: &str) -> Result<(), &'static str> {
+        let alarm = self
+            .list
+            .iter_mut()
+            .find(|a| a.selected)
+            .ok_or("No alarm selected.")?;
+
+        let mut label = label.trim().to_string();
+        if label.is_empty() {
+            label = alarm.spec();
+        }
+        grapheme_truncate(&mut label, LABEL_SIZE_LIMIT, '…');
+        label.shrink_to_fit();
+        alarm.label = label;
         Ok(())
     }
 
-    // Remove last alarm.
-    pub fn pop(&mut self) -> Option<Alarm> {
-        self.list.pop()
+    // Adjust scrolling offset so the selected alarm is visible.
+    fn scroll_to_selection(&mut self, layout: &Layout) {
+        let index = match self.selected() {
+            Some(index) => index,
+            None => return,
+        };
+        let height = (layout.roster_height as usize).max(1);
+
+        // The first visible line holds a placeholder when scrolled down.
+        if index < self.offset || (self.offset > 0 && index == self.offset) {
+            self.offset = index.saturating_sub(1);
+        } else if index >= self.offset + height {
+            self.offset = index + 1 - height;
+        }
+        self.adjust_offset(layout);
     }
 
     // Offset ceiling according to layout information.
@@ -229,11 +909,157 @@ impl AlarmRoster {
         );
     }
 
+    // Write state of all alarms to the session file.
+    pub fn save<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        for alarm in &self.list {
+            writeln!(out, "alarm {}", alarm.state())?;
+        }
+        Ok(())
+    }
+
+    // Restore alarm from its state in the session file.
+    pub fn restore(&mut self, state: &str) -> Result<(), &'static str> {
+        let mut fields = state.splitn(3, ' ');
+        let (flags, time, input) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(flags), Some(time), Some(input)) => (flags, time, input),
+            _ => return Err("Malformed alarm."),
+        };
+        let time: u64 = time.parse().map_err(|_| "Malformed alarm time.")?;
+        let index = if flags.contains('z') || flags.contains('s') {
+            // Snoozed copies and alarms of steps go off at "time".
+            let (spec, label) = input.split_once(DELIMITER).ok_or("Malformed alarm.")?;
+            let step = if flags.contains('s') {
+                Some(parse_time(spec)?)
+            } else {
+                None
+            };
+            let time = u32::try_from(time).unwrap_or(u32::MAX);
+            let index = self.insert(label.to_string(), time, None, None);
+            self.list[index].step = step;
+            index
+        } else {
+            // Restored alarms are not checked for duplicates.
+            let spec = Spec::parse(input)?;
+            self.insert(spec.label, spec.time, spec.wall, spec.repeat)
+        };
+        let alarm = &mut self.list[index];
+
+        if let Some(wall) = &mut alarm.wall {
+            wall.due = UNIX_EPOCH + time::Duration::from_secs(time);
+        } else if alarm.repeat.is_some() {
+            // Skip occurrences passed before "time".
+            let time = u32::try_from(time).unwrap_or(u32::MAX);
+            alarm.rewind(time.saturating_sub(1));
+        }
+        alarm.exceeded = flags.contains('x');
+        alarm.pending = flags.contains('p');
+        alarm.snoozed = flags.contains('z');
+        self.list.sort_by_key(|a| a.time);
+        Ok(())
+    }
+
+    // Check for alarms which should have gone off already.
+    pub fn overdue(&self, clock: &Clock) -> bool {
+        let now = SystemTime::now();
+        self.list.iter().filter(|a| !a.exceeded).any(|a| match &a.wall {
+            Some(wall) => wall.due <= now,
+            None => a.time <= clock.total(),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
     // Check for active alarms.
     pub fn idle(&self) -> bool {
         !self.list.iter().any(|a| !a.exceeded)
     }
 
+    // Check for alarms not acknowledged yet.
+    pub fn pending(&self) -> bool {
+        self.list.iter().any(|a| a.pending)
+    }
+
+    // Alarms not acknowledged yet.
+    pub fn pending_alarms(&self) -> impl Iterator<Item = &Alarm> {
+        self.list.iter().filter(|a| a.pending)
+    }
+
+    // Acknowledge all pending alarms.
+    pub fn acknowledge(&mut self) -> bool {
+        let pending = self.pending();
+        for alarm in self.list.iter_mut() {
+            alarm.pending = false;
+        }
+        pending
+    }
+
+    // Raise pending alarms again if they were last notified longer than
+    // "interval" seconds ago. Returns a copy of the alarm to notify about.
+    pub fn nag(&mut self, interval: u32) -> Option<Alarm> {
+        let mut ret = None;
+        let interval = time::Duration::from_secs(interval as u64);
+
+        for alarm in self.list.iter_mut().filter(|a| a.pending) {
+            if alarm.notified.map_or(true, |n| n.elapsed() >= interval) {
+                alarm.notified = Some(time::Instant::now());
+                ret = Some(alarm.clone());
+            }
+        }
+        ret
+    }
+
+    // Snooze pending alarms, raising them again after "delay" seconds.
+    // Returns false if there was nothing to snooze.
+    pub fn snooze(&mut self, clock: &Clock, delay: u32) -> bool {
+        let time = clock.total().saturating_add(delay);
+        let mut copies = Vec::new();
+        let mut found = false;
+
+        for alarm in self.list.iter_mut().filter(|a| a.pending) {
+            found = true;
+            alarm.pending = false;
+            if alarm.snoozed {
+                // Snooze again.
+                alarm.time = time;
+                alarm.exceeded = false;
+            } else {
+                // Keep the original alarm and add a one-off copy.
+                let mut copy = alarm.clone();
+                copy.time = time;
+                copy.exceeded = false;
+                copy.wall = None;
+                copy.repeat = None;
+                copy.notified = None;
+                copy.snoozed = true;
+                copy.selected = false;
+                copy.step = None;
+                copy.emit(&mut self.events, "alarm_added");
+                copies.push(copy);
+            }
+        }
+        self.list.append(&mut copies);
+        self.list.sort_by_key(|a| a.time);
+        found
+    }
+
+    // Check for active alarms set to a time of day.
+    pub fn time_of_day_pending(&self) -> bool {
+        self.list.iter().any(|a| !a.exceeded && a.wall.is_some())
+    }
+
+    // Time left until the next alarm set to a time of day is due.
+    pub fn until_time_of_day(&self) -> Option<time::Duration> {
+        let now = SystemTime::now();
+        self.list
+            .iter()
+            .filter(|a| !a.exceeded)
+            .filter_map(|a| a.wall.as_ref())
+            .map(|wall| wall.due.duration_since(now).unwrap_or_default())
+            .min()
+    }
+
     pub fn scroll_up(&mut self, layout: &Layout) {
         let excess = self
             .list
@@ -250,39 +1076,77 @@ impl AlarmRoster {
         self.offset = excess.min(self.offset.saturating_add(1));
     }
 
-    // Find and process exceeded alarms.
+    // Find and process exceeded alarms. Returns a copy of the last alarm
+    // raised.
     pub fn check(
         &mut self,
         clock: &mut Clock,
         layout: &Layout,
         countdown: &mut Countdown,
-        force_redraw: bool,
-    ) -> Option<&Alarm> {
+        force_redraw: &mut bool,
+    ) -> Option<Alarm> {
         let mut ret = None;
+        let total = clock.total();
 
-        for (index, alarm) in self
+        // Update alarms set to a time of day.
+        let now = SystemTime::now();
+        for alarm in self.list.iter_mut().filter(|a| !a.exceeded) {
+            if let Some(wall) = &alarm.wall {
+                let remaining = match wall.due.duration_since(now) {
+                    // Round up to full seconds.
+                    Ok(d) if d.subsec_nanos() > 0 => d.as_secs() + 1,
+                    Ok(d) => d.as_secs(),
+                    Err(_) => 0,
+                };
+                alarm.time = total.saturating_add(remaining as u32);
+            }
+        }
+
+        for alarm in self
             .list
             .iter_mut()
-            .enumerate()
             // Ignore alarms marked exceeded.
-            .filter(|(_, a)| !a.exceeded)
+            .filter(|a| !a.exceeded && a.time <= total)
         {
-            if alarm.time <= clock.elapsed {
-                // Found alarm to raise.
-                alarm.exceeded = true;
-                clock.color_index = Some(alarm.color_index);
-                countdown.reset();
-                ret = Some(&*alarm);
-                // Skip ahead to the next one.
-                continue;
+            // Found alarm to raise.
+            clock.color_index = Some(alarm.color_index);
+            countdown.reset();
+            ret = Some(alarm.clone());
+            alarm.raise(total);
+            // Alarms passed while the clock is paused are not announced, so
+            // they need no acknowledgement. Steps still wait for it.
+            if clock.paused && alarm.wall.is_none() && alarm.step.is_none() {
+                alarm.pending = false;
+            } else {
+                self.events.push(
+                    "alarm_fired",
+                    &[
+                        ("label", Value::Str(&alarm.label)),
+                        ("alarm_time", Value::Num(alarm.nominal_time() as i64)),
+                        ("elapsed", Value::Num(total as i64)),
+                    ],
+                );
             }
-            // Reached the alarm to exceed next. Update countdown accordingly.
-            countdown.set(alarm.time - clock.elapsed);
-            if !countdown.has_position() || force_redraw {
-                countdown.place(&layout, &alarm, self.offset, index);
+        }
+
+        // Alarms set to a time of day may change order while the clock is
+        // paused, repeating alarms whenever they are raised.
+        if !self.list.windows(2).all(|pair| pair[0].time <= pair[1].time) {
+            self.list.sort_by_key(|a| a.time);
+            *force_redraw = true;
+        }
+
+        // Update countdown to the alarm to exceed next.
+        if let Some((index, alarm)) = self
+            .list
+            .iter()
+            .enumerate()
+            .find(|(_, a)| !a.exceeded)
+        {
+            countdown.set(alarm.time.saturating_sub(total));
+            if !countdown.has_position() || *force_redraw {
+                countdown.place(layout, alarm, self.offset, index);
             }
-            // Ignore other alarms.
-            break;
         }
         ret // Return value.
     }
@@ -292,9 +1156,10 @@ impl AlarmRoster {
         &mut self,
         stdout: &mut RawTerminal<W>,
         layout: &mut Layout,
+        palette: &Palette,
     ) -> Result<(), std::io::Error> {
         // Adjust offset in case something changed, e.g. the terminal size.
-        self.adjust_offset(&layout);
+        self.adjust_offset(layout);
 
         for (i, alarm) in self.list.iter().skip(self.offset).enumerate() {
             let line = layout.roster.line + i as u16;
@@ -327,40 +1192,69 @@ impl AlarmRoster {
                 break;
             }
 
+            // Let unacknowledged alarms blink.
+            if alarm.pending {
+                write!(stdout, "{}", style::Blink)?;
+            }
+            // Underline selected alarm.
+            let label = if alarm.selected {
+                format!("{}{}{}", style::Underline, alarm.label, style::NoUnderline)
+            } else {
+                alarm.label.clone()
+            };
+
             match alarm.exceeded {
                 true => {
                     write!(
                         stdout,
                         "{}{}{}{} {} {}{}",
                         cursor::Goto(layout.roster.col, line),
-                        color::Fg(COLOR[alarm.color_index]),
+                        color::Fg(palette.get(alarm.color_index)),
                         style::Bold,
                         style::Invert,
-                        &alarm.label,
+                        label,
                         style::Reset,
                         color::Fg(color::Reset),
                     )?;
+                    if let Some(note) = alarm.note() {
+                        write!(stdout, "{}{}{}", style::Faint, note, style::Reset)?;
+                    }
                 }
                 false => {
                     write!(
                         stdout,
                         "{}{} {} {}",
                         cursor::Goto(layout.roster.col, line),
-                        color::Bg(COLOR[alarm.color_index]),
+                        color::Bg(palette.get(alarm.color_index)),
                         color::Bg(color::Reset),
-                        &alarm.label,
+                        label,
                     )?;
+                    if let Some(note) = alarm.note() {
+                        write!(stdout, " {}{}{}", style::Faint, note, style::Reset)?;
+                    }
                 }
             }
+            if alarm.pending {
+                write!(stdout, "{}", style::Reset)?;
+            }
+        }
+
+        // Laps go below the alarms, separated by an empty line.
+        let used = match self.list.len().saturating_sub(self.offset) as u16 {
+            0 => 0,
+            alarms => alarms.min(layout.roster_height + 1) + 1,
+        };
+        if let Some(lines) = (layout.roster_height + 1).checked_sub(used) {
+            self.laps.draw(stdout, layout.roster.col + 2, layout.roster.line + used, lines)?;
         }
         Ok(())
     }
 
     // Return width of roster.
     pub fn width(&self) -> u16 {
-        let mut width: u16 = 0;
+        let mut width: u16 = self.laps.width();
         for alarm in &self.list {
-            let length = UnicodeWidthStr::width(alarm.label.as_str()) as u16;
+            let length = alarm.width();
             if length > width {
                 width = length
             };
@@ -373,10 +1267,35 @@ impl AlarmRoster {
         }
     }
 
+    // Add alarm ending a step of a sequence at "time". "step" holds the
+    // duration of the step.
+    pub fn add_step(&mut self, time: u32, step: u32, label: &str) {
+        let index = self.insert(label.to_string(), time, None, None);
+        self.list[index].step = Some(step);
+        self.list[index].emit(&mut self.events, "alarm_added");
+    }
+
+    // State of the alarm ending the current step: exceeded and pending.
+    pub fn step_state(&self) -> Option<(bool, bool)> {
+        self.list
+            .iter()
+            .rev()
+            .find(|a| a.step.is_some())
+            .map(|a| (a.exceeded, a.pending))
+    }
+
+    // Remove alarms of all steps.
+    pub fn remove_steps(&mut self) {
+        self.list.retain(|a| a.step.is_none());
+    }
+
     // Reset every alarm.
     pub fn reset_all(&mut self) {
+        // Drop snoozed copies.
+        self.list.retain(|a| !a.snoozed);
+        self.laps.clear();
         for alarm in &mut self.list {
-            alarm.reset();
+            alarm.rewind(0);
         }
     }
 
@@ -385,11 +1304,9 @@ impl AlarmRoster {
         clock.color_index = None;
 
         for alarm in self.list.iter_mut() {
-            if alarm.time <= clock.elapsed {
-                alarm.exceeded = true;
+            alarm.rewind(clock.total());
+            if alarm.has_passed() {
                 clock.color_index = Some(alarm.color_index);
-            } else {
-                alarm.exceeded = false;
             }
         }
     }
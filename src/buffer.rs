@@ -24,7 +24,23 @@ use termion::{clear, color, cursor};
 use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-const PROMPT: &str = "Add alarm: ";
+// What to do with the buffer content.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mode {
+    Add,
+    Edit,
+    Label,
+}
+
+impl Mode {
+    fn prompt(&self) -> &'static str {
+        match self {
+            Mode::Add => "Add alarm: ",
+            Mode::Edit => "Edit alarm: ",
+            Mode::Label => "Label: ",
+        }
+    }
+}
 
 // Input buffer.
 pub struct Buffer {
@@ -32,6 +48,7 @@ pub struct Buffer {
     // Used for error messages.
     message: Option<&'static str>,
     pub visible: bool,
+    pub mode: Mode,
 }
 
 impl Buffer {
@@ -40,9 +57,18 @@ impl Buffer {
             content: String::new(),
             message: None,
             visible: false,
+            mode: Mode::Add,
         }
     }
 
+    // Show buffer with the given content.
+    pub fn open(&mut self, mode: Mode, content: &str) {
+        self.message = None;
+        self.mode = mode;
+        self.content = content.to_string();
+        self.visible = true;
+    }
+
     // Return reference to buffer content.
     pub fn read(&mut self) -> &String {
         &self.content
@@ -71,9 +97,9 @@ impl Buffer {
     pub fn strip_word(&mut self) {
         // Reset error message.
         self.message = None;
-        let iter = UnicodeSegmentation::split_word_bound_indices(self.content.as_str().trim_end());
+        let mut iter = UnicodeSegmentation::split_word_bound_indices(self.content.as_str().trim_end());
 
-        if let Some((index, _)) = iter.last() {
+        if let Some((index, _)) = iter.next_back() {
             self.content.truncate(index);
         }
     }
@@ -85,6 +111,7 @@ impl Buffer {
     // Clear input.
     pub fn clear(&mut self) {
         self.content.clear();
+        self.mode = Mode::Add;
     }
 
     // Clear input and message.
@@ -99,6 +126,14 @@ impl Buffer {
         stdout: &mut RawTerminal<W>,
         layout: &mut Layout,
     ) -> Result<(), std::io::Error> {
+        let prompt = self.mode.prompt();
+
+        // Leave the only line of a one-line window to the clock, unless
+        // taking input.
+        if layout.height == 1 && !self.visible {
+            return Ok(());
+        }
+
         // Write error message if present and return.
         if let Some(msg) = self.message {
             write!(
@@ -107,7 +142,7 @@ impl Buffer {
                 cursor::Hide,
                 cursor::Goto(layout.buffer.col, layout.buffer.line),
                 clear::CurrentLine,
-                PROMPT,
+                prompt,
                 color::Fg(color::LightRed),
                 &msg,
                 color::Fg(color::Reset)
@@ -115,7 +150,7 @@ impl Buffer {
             return Ok(());
         }
 
-        if self.content.is_empty() {
+        if !self.visible {
             // Clear buffer display.
             write!(
                 stdout,
@@ -126,7 +161,7 @@ impl Buffer {
             )?;
         } else {
             // Check if buffer exceeds limits.
-            while UnicodeWidthStr::width(self.content.as_str()) + UnicodeWidthStr::width(PROMPT)
+            while UnicodeWidthStr::width(self.content.as_str()) + UnicodeWidthStr::width(prompt)
                 > layout.width as usize
             {
                 self.content.pop();
@@ -137,7 +172,7 @@ impl Buffer {
                 "{}{}{}{}{}",
                 cursor::Goto(layout.buffer.col, layout.buffer.line),
                 clear::CurrentLine,
-                PROMPT,
+                prompt,
                 cursor::Show,
                 &self.content
             )?;
@@ -15,159 +15,304 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
+pub mod figlet;
 pub mod font;
 
-use crate::consts::COLOR;
 use crate::layout::{Layout, Position};
+use crate::palette::Palette;
+use crate::utils::format_duration;
 use crate::Config;
 use std::io::Write;
 use std::time;
 use termion::raw::RawTerminal;
 use termion::{color, cursor, style};
 
-enum Pause {
-    Instant(time::Instant),
-    Time((u32, u32)),
-    None,
-}
+const DAY: time::Duration = time::Duration::from_secs(24 * 60 * 60);
 
 pub struct Clock {
     pub start: time::Instant,
-    pub elapsed: u32,
+    // Time elapsed on the current day. Kept while paused.
+    pub elapsed: time::Duration,
     pub days: u32,
     pub paused: bool,
-    paused_at: Pause,
     pub color_index: Option<usize>,
+    // Color of the current step. Alarms going off take precedence.
+    pub step_color: Option<color::AnsiValue>,
     pub font: &'static font::Font,
+    // Countdown target in seconds.
+    pub target: Option<u32>,
+    // Display time remaining until "target" instead of elapsed time.
+    pub count_down: bool,
+    // Flash to draw attention to unacknowledged alarms.
+    pub flash: bool,
+    // Display tenths of a second.
+    pub tenths: bool,
+    // Scale font to fill the window.
+    pub scale: bool,
 }
 
 impl Clock {
     pub fn new(config: &Config) -> Clock {
         Clock {
             start: time::Instant::now(),
-            elapsed: 0,
+            elapsed: time::Duration::ZERO,
             days: 0,
             paused: false,
-            paused_at: Pause::None,
             color_index: None,
+            step_color: None,
             font: config.font,
+            target: None,
+            count_down: false,
+            flash: false,
+            tenths: config.tenths,
+            scale: config.scale,
         }
     }
 
     pub fn reset(&mut self) {
         self.start = time::Instant::now();
-        self.elapsed = 0;
+        self.elapsed = time::Duration::ZERO;
         self.days = 0;
         self.color_index = None;
-
-        // unpause will panic if we do not trigger a new pause here.
-        if self.paused {
-            self.pause();
-        }
     }
 
-    fn pause(&mut self) {
-        self.paused_at = Pause::Instant(time::Instant::now());
-        self.paused = true;
+    // Set clock to the given total time in seconds. Used to resume a session.
+    pub fn restore(&mut self, total: u32, paused: bool) {
+        self.days = total / (24 * 60 * 60);
+        self.elapsed = time::Duration::from_secs((total % (24 * 60 * 60)) as u64);
+        self.paused = paused;
+        self.resume_at_elapsed();
     }
 
-    fn unpause(&mut self) {
-        match self.paused_at {
-            Pause::Instant(delay) => {
-                if let Some(start) = self.start.checked_add(delay.elapsed()) {
-                    self.start = start;
-                }
-            }
-            Pause::Time((secs, _days)) => {
-                self.start = time::Instant::now() - time::Duration::from_secs(secs as u64);
-            }
-            Pause::None => (), // O_o
+    // Let "start" lie "elapsed" in the past, so the clock runs on from there.
+    fn resume_at_elapsed(&mut self) {
+        if let Some(start) = time::Instant::now().checked_sub(self.elapsed) {
+            self.start = start;
         }
+    }
 
-        self.paused_at = Pause::None;
-        self.paused = false;
+    // Bring elapsed time up to date. Returns true if a new day began.
+    pub fn sync(&mut self) -> bool {
+        if self.paused {
+            return false;
+        }
+        // Should never overflow as we reestablish a new "start" instant
+        // every 24 hours.
+        self.elapsed = self.start.elapsed();
+        if self.elapsed < DAY {
+            return false;
+        }
+        self.next_day();
+        true
     }
 
+    // Pausing keeps the elapsed time of the last sync.
     pub fn toggle(&mut self) {
         if self.paused {
-            self.unpause();
-        } else {
-            self.pause();
+            self.resume_at_elapsed();
         }
+        self.paused = !self.paused;
     }
 
-    pub fn shift(&mut self, shift: i32) {
-        let secs = if shift.is_negative() {
-            if self.elapsed < shift.abs() as u32 && self.days > 0 {
-                // Negative day shift.
-                self.days -= 1;
-                self.elapsed += 24 * 60 * 60;
-            }
-            self.elapsed.saturating_sub(shift.abs() as u32)
+    // Switch between counting up and counting down. Returns false if there is
+    // no target to count down to.
+    pub fn toggle_direction(&mut self) -> bool {
+        if self.target.is_some() {
+            self.count_down = !self.count_down;
+            true
         } else {
-            self.elapsed.saturating_add(shift as u32)
-        };
-        let days = self.days.saturating_add(secs / (24 * 60 * 60));
-        let secs = secs % (24 * 60 * 60);
+            false
+        }
+    }
+
+    // Total elapsed time in seconds, including days.
+    pub fn total(&self) -> u32 {
+        self.days
+            .saturating_mul(24 * 60 * 60)
+            .saturating_add(self.elapsed.as_secs() as u32)
+    }
 
-        self.paused_at = Pause::Time((secs, days));
-        self.elapsed = secs;
-        self.days = days;
+    // Total elapsed time with sub-second precision.
+    pub fn precise(&self) -> time::Duration {
+        DAY * self.days + self.elapsed
     }
 
-    pub fn get_width(&self) -> u16 {
-        if self.elapsed >= 3600 {
-            // Hours
-            self.font.width * 6 + 3 + 10
+    // Tenth of a second shown. None if tenths are not displayed.
+    pub fn tenth(&self) -> Option<u32> {
+        if self.tenths {
+            Some(self.elapsed.subsec_millis() / 100)
         } else {
-            // Minutes and seconds only.
-            self.font.width * 4 + 2 + 5
+            None
+        }
+    }
+
+    // Time left until the display changes next. None if paused.
+    pub fn until_next(&self) -> Option<time::Duration> {
+        if self.paused {
+            return None;
         }
+        let step: u128 = if self.tenths { 100_000_000 } else { 1_000_000_000 };
+        let passed = self.elapsed.as_nanos() % step;
+        Some(time::Duration::from_nanos((step - passed) as u64))
     }
 
-    pub fn next_day(&mut self) {
-        // Shift start 24h into the future.
-        let next = self.start.clone() + time::Duration::from_secs(60 * 60 * 24);
+    // Return the value to display in seconds and whether the countdown
+    // target has been exceeded.
+    pub fn value(&self) -> (u32, bool) {
+        match self.target {
+            Some(target) if self.count_down => {
+                let total = self.total();
+                if total > target {
+                    (total - target, true)
+                } else {
+                    (target - total, false)
+                }
+            }
+            _ => (self.total(), false),
+        }
+    }
 
-        // Take care not to shift start into the future.
-        if next <= time::Instant::now() {
-            self.start = next;
-            self.elapsed = 0;
-            self.days = self.days.saturating_add(1);
+    // Returns true if the displayed value just crossed the hour barrier, in
+    // which case the hour digits appear or vanish.
+    pub fn hour_barrier(&self) -> bool {
+        match self.value() {
+            (value, false) if self.count_down => {
+                // Hours also appear when dropping below a full day.
+                let secs = value % (24 * 60 * 60);
+                secs == 3599 || secs == 24 * 60 * 60 - 1
+            }
+            (value, _) => value % (24 * 60 * 60) == 3600,
         }
     }
 
+    // Returns true if hours are displayed.
+    pub fn shows_hours(&self) -> bool {
+        self.value().0 % (24 * 60 * 60) >= 3600
+    }
+
+    pub fn shift(&mut self, shift: i32) {
+        // Shifting back may take off several days.
+        let total = if shift.is_negative() {
+            self.total().saturating_sub(shift.unsigned_abs())
+        } else {
+            self.total().saturating_add(shift as u32)
+        };
+        self.restore(total, self.paused);
+    }
+
+    fn next_day(&mut self) {
+        // Shift start 24h into the future. Never shifts start into the
+        // future, as a full day has elapsed.
+        self.start += DAY;
+        self.elapsed -= DAY;
+        self.days = self.days.saturating_add(1);
+    }
+
     // Draw clock according to layout.
     pub fn draw<W: Write>(
         &self,
-        mut stdout: &mut RawTerminal<W>,
+        stdout: &mut RawTerminal<W>,
         layout: &Layout,
+        palette: &Palette,
         force_redraw: bool,
     ) -> Result<(), std::io::Error> {
+        let (mut value, overtime) = self.value();
+        // Show the time remaining when counting down, not the one elapsed.
+        let tenth = match self.elapsed.subsec_millis() / 100 {
+            tenth if self.tenths && self.count_down && !overtime && value > 0 && tenth > 0 => {
+                value -= 1;
+                10 - tenth
+            }
+            tenth => tenth,
+        };
+        // Digits change at different points in time when counting down, so we
+        // simply redraw everything in that case. Same when flashing.
+        let force_redraw = force_redraw || self.count_down || self.flash;
+        // Flash by leaving out color every other second.
+        let paint: Option<&dyn color::Color> = match (self.color_index, &self.step_color) {
+            _ if self.flash && self.total() % 2 == 1 => None,
+            (Some(c), _) => Some(palette.get(c)),
+            (None, Some(c)) => Some(c),
+            (None, None) => None,
+        };
+
         // Setup style and color if appropriate.
         if self.paused {
             write!(stdout, "{}", style::Faint)?;
         }
-        if let Some(c) = self.color_index {
-            write!(stdout, "{}", color::Fg(COLOR[c]))?;
+        if let Some(paint) = paint {
+            write!(stdout, "{}", color::Fg(paint))?;
         }
 
+        if layout.compact {
+            self.draw_compact(stdout, layout, value, tenth, overtime)?;
+        } else {
+            self.draw_digits(stdout, layout, force_redraw, value, tenth, overtime)?;
+        }
+
+        // Reset color and style.
+        if self.paused || paint.is_some() {
+            write!(stdout, "{}{}", style::NoFaint, color::Fg(color::Reset),)?;
+        }
+        Ok(())
+    }
+
+    // Draw clock as a single line of text.
+    fn draw_compact<W: Write>(
+        &self,
+        stdout: &mut RawTerminal<W>,
+        layout: &Layout,
+        value: u32,
+        tenth: u32,
+        overtime: bool,
+    ) -> Result<(), std::io::Error> {
+        let mut text = format_duration(value);
+        if self.tenths {
+            text.push_str(&format!(".{}", tenth));
+        }
+        if self.count_down && !overtime {
+            text.insert(0, '-');
+        }
+        // Overwrite leftovers of longer values.
+        write!(
+            stdout,
+            "{}{:<width$}",
+            cursor::Goto(layout.clock_sec.col, layout.clock_sec.line),
+            text,
+            width = (layout.next_alarm.col - layout.clock_sec.col) as usize,
+        )
+    }
+
+    // Draw clock in big digits.
+    fn draw_digits<W: Write>(
+        &self,
+        stdout: &mut RawTerminal<W>,
+        layout: &Layout,
+        force_redraw: bool,
+        value: u32,
+        tenth: u32,
+        overtime: bool,
+    ) -> Result<(), std::io::Error> {
+        let days = value / (24 * 60 * 60);
+        let secs = value % (24 * 60 * 60);
+
         // Run once every hour or on request.
-        if force_redraw || self.elapsed % 3600 == 0 {
+        if force_redraw || secs % 3600 == 0 {
             // Draw hours if necessary.
-            if self.elapsed >= 3600 {
-                self.draw_digit_pair(&mut stdout, self.elapsed / 3600, &layout.clock_hr)?;
+            if secs >= 3600 {
+                self.draw_digit_pair(stdout, layout.font, secs / 3600, &layout.clock_hr)?;
 
                 // Draw colon.
-                self.draw_colon(&mut stdout, &layout.clock_colon1)?;
+                self.draw_colon(stdout, layout.font, &layout.clock_colon1)?;
             }
 
             // Draw days.
-            if self.days > 0 {
+            if days > 0 {
                 let day_count = format!(
-                    "+ {} {}",
-                    self.days,
-                    if self.days == 1 { "DAY" } else { "DAYS" },
+                    "{} {} {}",
+                    if overtime || !self.count_down { '+' } else { '-' },
+                    days,
+                    if days == 1 { "DAY" } else { "DAYS" },
                 );
 
                 write!(
@@ -176,25 +321,56 @@ impl Clock {
                     cursor::Goto(layout.clock_days.col, layout.clock_days.line,),
                     day_count,
                 )?;
+            } else if self.count_down {
+                write!(
+                    stdout,
+                    "{}{:>11}",
+                    cursor::Goto(layout.clock_days.col, layout.clock_days.line,),
+                    if overtime { "OVERTIME" } else { "REMAINING" },
+                )?;
             }
         }
 
         // Draw minutes if necessary. Once every minute or on request.
-        if force_redraw || self.elapsed % 60 == 0 {
-            self.draw_digit_pair(&mut stdout, (self.elapsed % 3600) / 60, &layout.clock_min)?;
+        if force_redraw || secs % 60 == 0 {
+            self.draw_digit_pair(stdout, layout.font, (secs % 3600) / 60, &layout.clock_min)?;
         }
 
         // Draw colon if necessary.
         if force_redraw {
-            self.draw_colon(&mut stdout, &layout.clock_colon0)?;
+            self.draw_colon(stdout, layout.font, &layout.clock_colon0)?;
         }
 
         // Draw seconds.
-        self.draw_digit_pair(&mut stdout, self.elapsed % 60, &layout.clock_sec)?;
+        self.draw_digit_pair(stdout, layout.font, secs % 60, &layout.clock_sec)?;
 
-        // Reset color and style.
-        if self.paused || self.color_index != None {
-            write!(stdout, "{}{}", style::NoFaint, color::Fg(color::Reset),)?;
+        // Draw tenths of a second, separated by a single dot.
+        if self.tenths {
+            if force_redraw {
+                let bottom = layout.clock_dot.line + layout.font.height - 1;
+                match layout.font.dot {
+                    Some(dot) => {
+                        for (i, row) in dot.iter().rev().enumerate() {
+                            let line = bottom - i as u16;
+                            write!(stdout, "{}{}", cursor::Goto(layout.clock_dot.col, line), row)?;
+                        }
+                    }
+                    None => write!(
+                        stdout,
+                        "{}{}",
+                        cursor::Goto(layout.clock_dot.col, bottom),
+                        layout.font.dots.1,
+                    )?,
+                }
+            }
+            for (i, row) in layout.font.digits[tenth as usize].iter().enumerate() {
+                write!(
+                    stdout,
+                    "{}{}",
+                    cursor::Goto(layout.clock_tenth.col, layout.clock_tenth.line + i as u16),
+                    row,
+                )?;
+            }
         }
         Ok(())
     }
@@ -202,11 +378,12 @@ impl Clock {
     fn draw_digit_pair<W: Write>(
         &self,
         stdout: &mut RawTerminal<W>,
+        font: &font::Font,
         value: u32,
         pos: &Position,
     ) -> Result<(), std::io::Error> {
-        let left = self.font.digits[value as usize / 10].iter();
-        let right = self.font.digits[value as usize % 10].iter();
+        let left = font.digits[value as usize / 10].iter();
+        let right = font.digits[value as usize % 10].iter();
 
         for (i, (left, right)) in left.zip(right).enumerate() {
             write!(
@@ -224,15 +401,24 @@ impl Clock {
     fn draw_colon<W: Write>(
         &self,
         stdout: &mut RawTerminal<W>,
+        font: &font::Font,
         pos: &Position,
     ) -> Result<(), std::io::Error> {
+        if let Some(colon) = font.colon {
+            for (i, row) in colon.iter().enumerate() {
+                write!(stdout, "{}{}", cursor::Goto(pos.col, pos.line + i as u16), row)?;
+            }
+            return Ok(());
+        }
+        // Place dots one line above and below the middle of the digits.
+        let middle = pos.line + font.height / 2;
         write!(
             stdout,
             "{}{}{}{}",
-            cursor::Goto(pos.col, pos.line + 1),
-            self.font.dots.0,
-            cursor::Goto(pos.col, pos.line + 3),
-            self.font.dots.1,
+            cursor::Goto(pos.col, middle - 1),
+            font.dots.0,
+            cursor::Goto(pos.col, middle + 1),
+            font.dots.1,
         )?;
         Ok(())
     }
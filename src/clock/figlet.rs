@@ -0,0 +1,132 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Import of FIGlet (.flf) and TOIlet (.tlf) fonts. After a header line and
+// comments, these hold the glyphs of ASCII characters 32 to 126 in order,
+// each row terminated by an end mark. We only need the digits and the colon.
+
+use super::font::{self, Font};
+use std::fs;
+use std::path::Path;
+use unicode_width::UnicodeWidthStr;
+
+// First character in the file and the ones we are after.
+const FIRST: u8 = b' ';
+const DIGIT_0: u8 = b'0';
+const COLON: u8 = b':';
+
+pub fn load(path: &str) -> Result<&'static Font, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Error reading FIGlet font {}. ({})", path, e))?;
+    let mut font = parse(&content)
+        .map_err(|(n, msg)| format!("Error in FIGlet font {}:{}. ({})", path, n, msg))?;
+    if let Some(stem) = Path::new(path).file_stem() {
+        font.name = Box::leak(stem.to_string_lossy().into_owned().into_boxed_str());
+    }
+    Ok(Box::leak(Box::new(font)))
+}
+
+// Parse FIGlet font. Returns the line number and a message on error.
+pub fn parse(content: &str) -> Result<Font, (usize, String)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let header = lines.first().copied().unwrap_or("");
+    let error = |n: usize, msg: &str| (n, msg.to_string());
+
+    // flf2a$ HEIGHT BASELINE MAX_LENGTH OLD_LAYOUT COMMENT_LINES ...
+    if !header.starts_with("flf2a") && !header.starts_with("tlf2a") {
+        return Err(error(1, "Not a FIGlet font."));
+    }
+    let hardblank = header.chars().nth(5).ok_or_else(|| error(1, "Missing hardblank."))?;
+    let params: Vec<i64> = header
+        .split_whitespace()
+        .skip(1)
+        .map(str::parse)
+        .collect::<Result<_, _>>()
+        .map_err(|_| error(1, "Invalid header."))?;
+    let (height, comments) = match params.as_slice() {
+        [height, _, _, _, comments, ..] if *height > 0 && *comments >= 0 => {
+            (*height as usize, *comments as usize)
+        }
+        _ => return Err(error(1, "Invalid header.")),
+    };
+
+    // Rows of the digits and the colon.
+    let mut glyphs: Vec<Vec<String>> = Vec::new();
+    for code in DIGIT_0..=COLON {
+        // Huge header values must not overflow.
+        let (start, end) = ((code - FIRST) as usize)
+            .checked_mul(height)
+            .and_then(|offset| offset.checked_add(1 + comments))
+            .and_then(|start| Some((start, start.checked_add(height)?)))
+            .ok_or_else(|| error(1, "Invalid header."))?;
+        let mut glyph = Vec::new();
+        for n in start..end {
+            let line = lines
+                .get(n)
+                .ok_or_else(|| error(lines.len(), "Missing digits or colon."))?;
+            if line.contains(char::is_control) {
+                return Err(error(n + 1, "Control characters are not allowed."));
+            }
+            // The last character is the end mark, doubled on the last row.
+            // Strip no more than two, the glyph may end in the same character.
+            let line = line.trim_end();
+            let row = match line.chars().last() {
+                Some(mark) => {
+                    let row = line.strip_suffix(mark).unwrap_or(line);
+                    row.strip_suffix(mark).unwrap_or(row)
+                }
+                None => return Err(error(n + 1, "Missing end mark.")),
+            };
+            glyph.push(row.replace(hardblank, " "));
+        }
+        glyphs.push(glyph);
+    }
+
+    trim_rows(&mut glyphs);
+    if glyphs[0].is_empty() {
+        return Err(error(lines.len(), "Empty digits."));
+    }
+    // Center digits of variable width in the widest one. Pad rows of the
+    // colon to a common width.
+    let glyph_width = |glyph: &[String]| glyph.iter().map(|row| row.width()).max().unwrap_or(0);
+    let width = glyphs[..10].iter().map(|glyph| glyph_width(glyph)).max().unwrap_or(0);
+    for (i, glyph) in glyphs.iter_mut().enumerate() {
+        let own = glyph_width(glyph);
+        let (total, left) = if i < 10 { (width, (width - own) / 2) } else { (own, 0) };
+        for row in glyph.iter_mut() {
+            let right = total - left - row.width();
+            *row = format!("{}{}{}", " ".repeat(left), row, " ".repeat(right));
+        }
+    }
+    font::assemble(glyphs).map_err(|msg| error(lines.len(), msg))
+}
+
+// Remove blank rows above and below all glyphs, usually room for ascenders and
+// descenders.
+fn trim_rows(glyphs: &mut [Vec<String>]) {
+    let blank = |glyphs: &[Vec<String>], i: usize| glyphs.iter().all(|g| g[i].trim().is_empty());
+    while !glyphs[0].is_empty() && blank(glyphs, glyphs[0].len() - 1) {
+        for glyph in glyphs.iter_mut() {
+            glyph.pop();
+        }
+    }
+    while !glyphs[0].is_empty() && blank(glyphs, 0) {
+        for glyph in glyphs.iter_mut() {
+            glyph.remove(0);
+        }
+    }
+}
@@ -15,21 +15,58 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
+// Built-in fonts and fonts loaded from files. A font file holds the glyphs of
+// the digits 0 to 9 and the colon. A line names the glyph, the indented lines
+// following it are its rows, written between two "|" to keep trailing spaces:
+//
+//   0
+//       |█▀█|
+//       |█ █|
+//       |▀▀▀|
+//
+// All digits must be of the same width and height, the colon of the same
+// height.
+
+use super::figlet;
+use crate::settings;
+use std::path::PathBuf;
+use std::{env, fs};
+use unicode_width::UnicodeWidthStr;
+
 const DIGIT_HEIGHT: u16 = 5;
 
 pub struct Font {
+    pub name: &'static str,
     pub height: u16,
     pub width: u16,
+    // Rows of the colon. Built-in fonts draw "dots" instead.
+    pub colon: Option<&'static [&'static str]>,
+    pub colon_width: u16,
+    // The second dot also separates tenths of a second.
     pub dots: (char, char),
-    pub digits: [[&'static str; DIGIT_HEIGHT as usize]; 10],
+    // Rows of the dot separating tenths, set for scaled fonts. Aligned to
+    // the bottom of the digits.
+    pub dot: Option<&'static [&'static str]>,
+    pub digits: [&'static [&'static str]; 10],
+}
+
+impl Font {
+    // Width of the dot separating tenths.
+    pub fn dot_width(&self) -> u16 {
+        self.dot.map_or(1, |dot| dot[0].width() as u16)
+    }
 }
 
 pub const NORMAL: Font = Font {
+    name: "normal",
     height: DIGIT_HEIGHT,
     width: 5,
+    colon: None,
+    colon_width: 1,
     dots: ('■', '■'),
+    dot: None,
     digits: [
-        [
+        &[
             // 0
             "█▀▀▀█",
             "█   █",
@@ -37,7 +74,7 @@ pub const NORMAL: Font = Font {
             "█   █",
             "█▄▄▄█",
         ],
-        [
+        &[
             // 1
             "  ▀█ ",
             "   █ ",
@@ -45,7 +82,7 @@ pub const NORMAL: Font = Font {
             "   █ ",
             "   █ ",
         ],
-        [
+        &[
             // 2
             "▀▀▀▀█",
             "    █",
@@ -53,7 +90,7 @@ pub const NORMAL: Font = Font {
             "█    ",
             "█▄▄▄▄",
         ],
-        [
+        &[
             // 3
             "▀▀▀▀█",
             "    █",
@@ -61,7 +98,7 @@ pub const NORMAL: Font = Font {
             "    █",
             "▄▄▄▄█",
         ],
-        [
+        &[
             // 4
             "█    ",
             "█  █ ",
@@ -69,7 +106,7 @@ pub const NORMAL: Font = Font {
             "   █ ",
             "   █ ",
         ],
-        [
+        &[
             // 5
             "█▀▀▀▀",
             "█    ",
@@ -77,7 +114,7 @@ pub const NORMAL: Font = Font {
             "    █",
             "▄▄▄▄█",
         ],
-        [
+        &[
             // 6
             "█    ",
             "█    ",
@@ -85,7 +122,7 @@ pub const NORMAL: Font = Font {
             "█   █",
             "█▄▄▄█",
         ],
-        [
+        &[
             // 7
             "▀▀▀▀█",
             "    █",
@@ -93,7 +130,7 @@ pub const NORMAL: Font = Font {
             "  █  ",
             "  █  ",
         ],
-        [
+        &[
             // 8
             "█▀▀▀█",
             "█   █",
@@ -101,7 +138,7 @@ pub const NORMAL: Font = Font {
             "█   █",
             "█▄▄▄█",
         ],
-        [
+        &[
             // 9
             "█▀▀▀█",
             "█   █",
@@ -113,11 +150,15 @@ pub const NORMAL: Font = Font {
 };
 
 pub const PLAIN: Font = Font {
+    name: "plain",
     height: DIGIT_HEIGHT,
     width: 5,
+    colon: None,
+    colon_width: 1,
     dots: ('█', '█'),
+    dot: None,
     digits: [
-        [
+        &[
             // 0
             "█████",
             "█   █",
@@ -125,7 +166,7 @@ pub const PLAIN: Font = Font {
             "█   █",
             "█████",
         ],
-        [
+        &[
             // 1
             "  ██ ",
             "   █ ",
@@ -133,7 +174,7 @@ pub const PLAIN: Font = Font {
             "   █ ",
             "   █ ",
         ],
-        [
+        &[
             // 2
             "█████",
             "    █",
@@ -141,7 +182,7 @@ pub const PLAIN: Font = Font {
             "█    ",
             "█████",
         ],
-        [
+        &[
             // 3
             "█████",
             "    █",
@@ -149,7 +190,7 @@ pub const PLAIN: Font = Font {
             "    █",
             "█████",
         ],
-        [
+        &[
             // 4
             "█    ",
             "█  █ ",
@@ -157,7 +198,7 @@ pub const PLAIN: Font = Font {
             "   █ ",
             "   █ ",
         ],
-        [
+        &[
             // 5
             "█████",
             "█    ",
@@ -165,7 +206,7 @@ pub const PLAIN: Font = Font {
             "    █",
             "█████",
         ],
-        [
+        &[
             // 6
             "█    ",
             "█    ",
@@ -173,7 +214,7 @@ pub const PLAIN: Font = Font {
             "█   █",
             "█████",
         ],
-        [
+        &[
             // 7
             "█████",
             "    █",
@@ -181,7 +222,7 @@ pub const PLAIN: Font = Font {
             "  █  ",
             "  █  ",
         ],
-        [
+        &[
             // 8
             "█████",
             "█   █",
@@ -189,7 +230,7 @@ pub const PLAIN: Font = Font {
             "█   █",
             "█████",
         ],
-        [
+        &[
             // 9
             "█████",
             "█   █",
@@ -200,13 +241,89 @@ pub const PLAIN: Font = Font {
     ],
 };
 
-/*
+// Fallback used when space is short.
+pub const SMALL: Font = Font {
+    name: "small",
+    height: 3,
+    width: 3,
+    colon: None,
+    colon_width: 1,
+    dots: ('▪', '▪'),
+    dot: None,
+    digits: [
+        &[
+            // 0
+            "█▀█",
+            "█ █",
+            "▀▀▀",
+        ],
+        &[
+            // 1
+            " ▀█",
+            "  █",
+            "  ▀",
+        ],
+        &[
+            // 2
+            "▀▀█",
+            "█▀▀",
+            "▀▀▀",
+        ],
+        &[
+            // 3
+            "▀▀█",
+            " ▀█",
+            "▀▀▀",
+        ],
+        &[
+            // 4
+            "█ █",
+            "▀▀█",
+            "  ▀",
+        ],
+        &[
+            // 5
+            "█▀▀",
+            "▀▀█",
+            "▀▀▀",
+        ],
+        &[
+            // 6
+            "█▀▀",
+            "█▀█",
+            "▀▀▀",
+        ],
+        &[
+            // 7
+            "▀▀█",
+            "  █",
+            "  ▀",
+        ],
+        &[
+            // 8
+            "█▀█",
+            "█▀█",
+            "▀▀▀",
+        ],
+        &[
+            // 9
+            "█▀█",
+            "▀▀█",
+            "▀▀▀",
+        ],
+    ],
+};
+
 pub const CHROME: Font = Font {
+    name: "chrome",
     height: DIGIT_HEIGHT,
     width: 5,
+    colon: None,
+    colon_width: 1,
     dots: ('▄', '🮏'),
+    dot: None,
     digits: [
-        [
+        &[
             // 0
             "█▀▀▀█",
             "█   █",
@@ -214,7 +331,7 @@ pub const CHROME: Font = Font {
             "🮐   🮐",
             "🮐🮏🮏🮏🮐",
         ],
-        [
+        &[
             // 1
             "  ▀█ ",
             "   █ ",
@@ -222,7 +339,7 @@ pub const CHROME: Font = Font {
             "   🮐 ",
             "   🮐 ",
         ],
-        [
+        &[
             // 2
             "▀▀▀▀█",
             "    █",
@@ -230,7 +347,7 @@ pub const CHROME: Font = Font {
             "🮐    ",
             "🮐🮏🮏🮏🮏",
         ],
-        [
+        &[
             // 3
             "▀▀▀▀█",
             "    █",
@@ -238,7 +355,7 @@ pub const CHROME: Font = Font {
             "    🮐",
             "🮏🮏🮏🮏🮐",
         ],
-        [
+        &[
             // 4
             "█    ",
             "█  █ ",
@@ -246,7 +363,7 @@ pub const CHROME: Font = Font {
             "   🮐 ",
             "   🮐 ",
         ],
-        [
+        &[
             // 5
             "█▀▀▀▀",
             "█    ",
@@ -254,7 +371,7 @@ pub const CHROME: Font = Font {
             "    🮐",
             "🮏🮏🮏🮏🮐",
         ],
-        [
+        &[
             // 6
             "█    ",
             "█    ",
@@ -262,7 +379,7 @@ pub const CHROME: Font = Font {
             "🮐   🮐",
             "🮐🮏🮏🮏🮐",
         ],
-        [
+        &[
             // 7
             "▀▀▀▀█",
             "    █",
@@ -270,7 +387,7 @@ pub const CHROME: Font = Font {
             "  🮐  ",
             "  🮐  ",
         ],
-        [
+        &[
             // 8
             "█▀▀▀█",
             "█   █",
@@ -278,7 +395,7 @@ pub const CHROME: Font = Font {
             "🮐   🮐",
             "🮐🮏🮏🮏🮐",
         ],
-        [
+        &[
             // 9
             "█▀▀▀█",
             "█   █",
@@ -288,4 +405,365 @@ pub const CHROME: Font = Font {
         ],
     ],
 };
-*/
+
+// Selectable built-in fonts. SMALL also serves as fallback if space is
+// short.
+pub const BUILTIN: [&Font; 4] = [&NORMAL, &PLAIN, &CHROME, &SMALL];
+
+// Read font file. Loaded fonts live for the rest of the program and are named
+// after the file.
+pub fn load(path: &str) -> Result<&'static Font, String> {
+    if path.ends_with(".flf") || path.ends_with(".tlf") {
+        return figlet::load(path);
+    }
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Error reading font file {}. ({})", path, e))?;
+    let mut font = parse(&content)
+        .map_err(|(n, msg)| format!("Error in font file {}:{}. ({})", path, n, msg))?;
+    if let Some(stem) = std::path::Path::new(path).file_stem() {
+        font.name = Box::leak(stem.to_string_lossy().into_owned().into_boxed_str());
+    }
+    Ok(Box::leak(Box::new(font)))
+}
+
+// Font files in the "fonts" directory next to the config file. The directory
+// is read once, files are parsed when first needed.
+#[derive(Default)]
+pub struct Fonts {
+    files: Vec<FontFile>,
+}
+
+struct FontFile {
+    name: String,
+    path: PathBuf,
+    // Result of loading the file, once tried.
+    font: Option<Result<&'static Font, String>>,
+}
+
+impl FontFile {
+    fn load(&mut self) -> &Result<&'static Font, String> {
+        let path = &self.path;
+        self.font.get_or_insert_with(|| load(&path.to_string_lossy()))
+    }
+}
+
+impl Fonts {
+    // List font files.
+    pub fn scan() -> Fonts {
+        let mut paths: Vec<PathBuf> = match settings::dir().map(|dir| fs::read_dir(dir.join("fonts"))) {
+            Some(Ok(entries)) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| path.is_file())
+                .collect(),
+            _ => Vec::new(),
+        };
+        paths.sort();
+        let files = paths
+            .into_iter()
+            .filter_map(|path| {
+                let name = path.file_stem()?.to_string_lossy().into_owned();
+                Some(FontFile { name, path, font: None })
+            })
+            .collect();
+        Fonts { files }
+    }
+
+    // Look up font by name, built-in ones first.
+    pub fn find(&mut self, name: &str) -> Result<&'static Font, String> {
+        if let Some(font) = BUILTIN.iter().find(|font| font.name == name) {
+            return Ok(font);
+        }
+        match self.files.iter_mut().find(|file| file.name == name) {
+            Some(file) => file.load().clone(),
+            None => Err(format!("Unknown font \"{}\".", name)),
+        }
+    }
+
+    // Names of built-in fonts and font files.
+    pub fn names(&self) -> Vec<String> {
+        BUILTIN
+            .iter()
+            .map(|font| font.name.to_string())
+            .chain(self.files.iter().map(|file| file.name.clone()))
+            .collect()
+    }
+
+    // Make "font" part of the cycle, replacing the file of the same name.
+    pub fn keep(&mut self, font: &'static Font) {
+        if BUILTIN.iter().any(|builtin| builtin.name == font.name) {
+            return;
+        }
+        match self.files.iter_mut().find(|file| file.name == font.name) {
+            Some(file) => file.font = Some(Ok(font)),
+            None => self.files.push(FontFile {
+                name: font.name.to_string(),
+                path: PathBuf::new(),
+                font: Some(Ok(font)),
+            }),
+        }
+    }
+
+    // Font following "current" among built-in fonts the terminal can display
+    // and font files. Broken files are skipped. Also returns whether one was
+    // found to be broken just now.
+    pub fn next(&mut self, current: &Font) -> (&'static Font, bool) {
+        let builtin: Vec<&'static Font> = BUILTIN
+            .iter()
+            .copied()
+            .filter(|font| supported(font, |var| env::var(var).ok()))
+            .collect();
+        let count = builtin.len() + self.files.len();
+        let name = |fonts: &Fonts, i: usize| match builtin.get(i) {
+            Some(font) => font.name.to_string(),
+            None => fonts.files[i - builtin.len()].name.clone(),
+        };
+        let start = (0..count)
+            .position(|i| name(self, i) == current.name)
+            .map_or(0, |i| i + 1);
+        let mut broken = false;
+        for i in (start..count).chain(0..start) {
+            if let Some(font) = builtin.get(i) {
+                return (font, broken);
+            }
+            let file = &mut self.files[i - builtin.len()];
+            let fresh = file.font.is_none();
+            match file.load() {
+                Ok(font) => return (font, broken),
+                Err(_) => broken |= fresh,
+            }
+        }
+        // The normal font is always displayed.
+        (&NORMAL, broken)
+    }
+}
+
+// Whether the terminal is likely to display the font. Symbols for legacy
+// computing (U+1FB00 and above) are missing on the Linux console and can
+// not be encoded in locales other than UTF-8.
+pub fn supported<F: Fn(&str) -> Option<String>>(font: &Font, var: F) -> bool {
+    let legacy = font
+        .digits
+        .iter()
+        .flat_map(|glyph| glyph.iter())
+        .flat_map(|row| row.chars())
+        .chain([font.dots.0, font.dots.1].iter().copied())
+        .any(|c| c >= '\u{1fb00}');
+    if !legacy {
+        return true;
+    }
+    if var("TERM").as_deref() == Some("linux") {
+        return false;
+    }
+    // The first locale variable set decides.
+    match ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|name| var(name))
+        .find(|value| !value.is_empty())
+    {
+        Some(locale) => {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        }
+        None => true,
+    }
+}
+
+// Parse font file. Returns the line number and a message on error.
+pub fn parse(content: &str) -> Result<Font, (usize, String)> {
+    // Rows of the digits and the colon, the latter at index 10.
+    let mut glyphs: Vec<Vec<String>> = vec![Vec::new(); 11];
+    let mut current: Option<usize> = None;
+    let mut lines = 0;
+
+    for (n, line) in content.lines().enumerate() {
+        let error = |msg: &str| (n + 1, msg.to_string());
+        lines = n + 1;
+        if line.trim().is_empty() || line.trim_start().starts_with('#') {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            let index = match line.trim() {
+                ":" => 10,
+                name => match name.parse::<usize>() {
+                    Ok(digit) if digit < 10 && name.len() == 1 => digit,
+                    _ => return Err(error("Expected a digit or \":\".")),
+                },
+            };
+            if !glyphs[index].is_empty() {
+                return Err(error("Glyph given twice."));
+            }
+            current = Some(index);
+            continue;
+        }
+        let index = current.ok_or_else(|| error("Row outside of glyph."))?;
+        let row = match line.trim().strip_prefix('|').and_then(|r| r.strip_suffix('|')) {
+            Some(row) => row,
+            None => return Err(error("Rows must be enclosed in \"|\".")),
+        };
+        if row.contains(char::is_control) {
+            return Err(error("Control characters are not allowed."));
+        }
+        if let Some(first) = glyphs[index].first() {
+            if first.width() != row.width() {
+                return Err(error("Rows differ in width."));
+            }
+        } else if row.width() == 0 {
+            return Err(error("Empty row."));
+        }
+        glyphs[index].push(row.to_string());
+    }
+
+    let error = |msg: &str| (lines, msg.to_string());
+    if let Some(digit) = glyphs.iter().position(Vec::is_empty) {
+        return Err(if digit < 10 {
+            (lines, format!("Missing digit {}.", digit))
+        } else {
+            error("Missing colon.")
+        });
+    }
+    let height = glyphs[0].len();
+    let width = glyphs[0][0].width();
+    if glyphs.iter().any(|glyph| glyph.len() != height) {
+        return Err(error("Glyphs differ in height."));
+    }
+    if glyphs[..10].iter().any(|glyph| glyph[0].width() != width) {
+        return Err(error("Digits differ in width."));
+    }
+    assemble(glyphs).map_err(error)
+}
+
+// Build font from the rows of the digits and the colon, the latter at index
+// 10. Rows must be of the same height and, apart from the colon, width.
+pub fn assemble(mut glyphs: Vec<Vec<String>>) -> Result<Font, &'static str> {
+    let colon = leak(glyphs.pop().ok_or("Missing colon.")?);
+    // Use the colon's outermost characters for the dot separating tenths.
+    let mut chars = colon.iter().flat_map(|row| row.chars()).filter(|c| !c.is_whitespace());
+    let first = chars.next().ok_or("Empty colon.")?;
+    let last = chars.next_back().unwrap_or(first);
+
+    let mut digits: [&'static [&'static str]; 10] = [&[]; 10];
+    for (digit, glyph) in digits.iter_mut().zip(glyphs) {
+        *digit = leak(glyph);
+    }
+    Ok(Font {
+        name: "",
+        height: colon.len() as u16,
+        width: digits[0].first().map_or(0, |row| row.width()) as u16,
+        colon_width: colon[0].width() as u16,
+        colon: Some(colon),
+        dots: (first, last),
+        dot: None,
+        digits,
+    })
+}
+
+// Rows of glyphs live as long as the program.
+fn leak(glyph: Vec<String>) -> &'static [&'static str] {
+    let rows: Vec<&'static str> = glyph
+        .into_iter()
+        .map(|row| &*Box::leak(row.into_boxed_str()))
+        .collect();
+    Box::leak(rows.into_boxed_slice())
+}
+
+// Font enlarged by "factor". Fonts drawn with block elements are scaled on a
+// grid of half blocks, others by repeating their characters. Glyphs are
+// leaked, so callers keep scaled fonts for reuse.
+pub fn scale(font: &Font, factor: u16) -> Font {
+    let factor = factor as usize;
+    let blocks = font
+        .digits
+        .iter()
+        .chain(font.colon.iter())
+        .flat_map(|glyph| glyph.iter())
+        .all(|row| row.chars().all(|c| half_blocks(c).is_some()));
+    let enlarge = |glyph: &[&str]| {
+        if blocks {
+            scale_blocks(glyph, factor)
+        } else {
+            glyph
+                .iter()
+                .flat_map(|row| {
+                    let row: String = row.chars().flat_map(|c| std::iter::repeat(c).take(factor)).collect();
+                    std::iter::repeat(row).take(factor)
+                })
+                .collect()
+        }
+    };
+    let mut glyphs: Vec<Vec<String>> = font.digits.iter().map(|glyph| enlarge(glyph)).collect();
+    glyphs.push(match font.colon {
+        Some(colon) => enlarge(colon),
+        // Square dots of full blocks match enlarged block elements best.
+        None if blocks => colon_from_dots(font.height, ('█', '█'), factor),
+        None => colon_from_dots(font.height, font.dots, factor),
+    });
+    let mut scaled = assemble(glyphs).expect("Scaled fonts are consistent.");
+    scaled.name = font.name;
+    scaled.dots = font.dots;
+    let dot = if blocks { '█' } else { font.dots.1 };
+    scaled.dot = Some(leak(dot_from_dots(dot, factor)));
+    scaled
+}
+
+// Upper and lower half of a character cell covered by block elements.
+fn half_blocks(c: char) -> Option<(bool, bool)> {
+    match c {
+        '█' => Some((true, true)),
+        '▀' => Some((true, false)),
+        '▄' => Some((false, true)),
+        ' ' => Some((false, false)),
+        _ => None,
+    }
+}
+
+fn scale_blocks(glyph: &[&str], factor: usize) -> Vec<String> {
+    // Two pixels per character cell, one on top of the other.
+    let mut pixels: Vec<Vec<bool>> = Vec::new();
+    for row in glyph {
+        let (upper, lower) = row.chars().filter_map(half_blocks).unzip();
+        pixels.push(upper);
+        pixels.push(lower);
+    }
+    let width = pixels[0].len() * factor;
+    let pixel = |y: usize, x: usize| pixels[y / factor][x / factor];
+    (0..glyph.len() * factor)
+        .map(|line| {
+            (0..width)
+                .map(|x| match (pixel(2 * line, x), pixel(2 * line + 1, x)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Dot separating tenths, enlarged by "factor" like the dots of the colon.
+fn dot_from_dots(dot: char, factor: usize) -> Vec<String> {
+    let row: String = std::iter::repeat(dot).take(factor).collect();
+    vec![row; (factor / 2).max(1)]
+}
+
+// Colon of a font drawn with "dots", enlarged by "factor". Keeps the gap
+// between the dots as high as the dots themselves.
+fn colon_from_dots(height: u16, dots: (char, char), factor: usize) -> Vec<String> {
+    let height = height as usize * factor;
+    let center = height / 2;
+    let dot = (factor / 2).max(1);
+    let upper = center - dot / 2 - dot..center - dot / 2;
+    let lower = center + dot.div_ceil(2)..center + dot.div_ceil(2) + dot;
+    (0..height)
+        .map(|line| {
+            let c = if upper.contains(&line) {
+                dots.0
+            } else if lower.contains(&line) {
+                dots.1
+            } else {
+                ' '
+            };
+            std::iter::repeat(c).take(factor).collect()
+        })
+        .collect()
+}
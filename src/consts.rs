@@ -33,29 +33,132 @@ pub mod ui {
     pub const USAGE: &str = concat!(
         "USAGE: ",
         env!("CARGO_PKG_NAME"),
-        " [-h|-v] [-e|--exec COMMAND] [-p] [-q] [ALARM[/LABEL]]
+        " [-h|-v] [-c|--countdown TIME] [-e|--exec COMMAND]
+                    [-n|--nag TIME] [-s|--snooze TIME] [-p] [-q] [-r]
+                    [--font NAME|--font-file PATH|--figlet-font PATH]
+                    [--socket PATH] [--status FORMAT] [--tenths] [--scale]
+                    [--notify BACKEND] [--title]
+                    [--events FILE|--events-fd FD] [--laps FILE]
+                    [--recipe NAME[=ALARM;...]] [--[auto-]steps STEPS]
+                    [--pomodoro WORK/SHORT/LONG[/COUNT]]
+                    [[-t|--timer NAME] [ALARM[/LABEL]]]
+       kitchentimer ctl [--socket PATH] [--timer NAME] COMMAND
 
 PARAMETERS:
   [ALARM TIME[/LABEL]]  Any number of alarm times (HH:MM:SS) with optional
-                        label.
+                        label. Prefix days like 2d04:00:00. Times may also
+                        be given with units, e.g. 5m, 1h30m, 90s, 2.5m or
+                        1d12h. Prefix a time of day with @ to set an alarm
+                        at that time, e.g. @18:30. Prefix with * or \"every\"
+                        to repeat an alarm at the given interval, limited
+                        by an optional xCOUNT or \"until TIME\", e.g.
+                        \"*10m x3/Stir\" or \"every 10:00 until 1h\".
 
 OPTIONS:
   -h, --help            Show this usage message.
   -v, --version         Show version information.
+  -c, --countdown TIME  Count down from TIME instead of counting up. Adds an
+                        alarm at TIME. Press [t] to switch directions.
   -e, --exec [COMMAND]  Execute COMMAND on alarm. My be given multiple
                         times. Occurrences of {l} will be replaced by the
-                        alarm label, {t} by alarm time in (HH:)MM:SS format.
+                        alarm label, {t} by alarm time in (HH:)MM:SS format,
+                        prefixed by days if necessary.
+  -n, --nag TIME        Repeat notification every TIME until an alarm is
+                        acknowledged with [a].
+  -s, --snooze TIME     Snooze alarms for TIME when pressing [s]. Defaults
+                        to 5m.
   -p, --plain           Use simpler block chars to draw the clock.
+  --font NAME           Draw the clock with font NAME, one of the fonts
+                        listed below. Press [f] to cycle through them.
+                        \"chrome\" needs a terminal supporting symbols for
+                        legacy computing and falls back to \"normal\" on the
+                        Linux console.
+  --font-file PATH      Draw the clock with the font read from PATH.
+  --figlet-font PATH    Draw the clock with the digits and the colon of the
+                        FIGlet or TOIlet font read from PATH.
   -q, --quit            Quit program after last alarm.
+  --tenths              Display tenths of a second.
+  --scale               Enlarge the clock to fill the window.
+  --title               Show the clock and the next alarm in the window
+                        title. The previous title is restored on exit.
+  --no-quit, --no-tenths, --no-scale, --no-title
+                        Switch off options set in the config file.
+  --notify BACKEND      Ask the terminal for a desktop notification on
+                        alarm. BACKEND is one of \"osc9\" (iTerm2, Windows
+                        Terminal), \"osc777\" (urxvt, foot, VTE), \"osc99\"
+                        (kitty), \"bell\" or \"auto\" (default), which picks
+                        one based on the environment and falls back to
+                        the bell.
+  -r, --resume          Resume the last session, e.g. after the terminal
+                        was closed. Alarms that went off in the meantime
+                        are raised. The session is kept in
+                        $XDG_STATE_HOME/kitchentimer/session.
+  --events FILE         Append events like alarms going off or the clock
+                        being paused to FILE, one JSON object per line.
+  --events-fd FD        Write events to the open file descriptor FD.
+  --laps FILE           Export laps taken with [ENTER] to FILE as CSV on
+                        exit or when pressing [w].
+  --socket PATH         Listen for commands on the control socket PATH.
+                        Defaults to $XDG_RUNTIME_DIR/kitchentimer.sock.
+  --status FORMAT       Print a status line with the clock and the next
+                        alarm once every second instead of running the
+                        user interface. FORMAT is one of \"plain\",
+                        \"i3bar\" or \"waybar\".
+  --recipe NAME         Add the alarms of recipe NAME. Recipes are read
+                        from $XDG_CONFIG_HOME/kitchentimer/recipes. Enter
+                        @NAME to add them interactively.
+  --recipe NAME=ALARM;...
+                        Define recipe NAME, e.g.
+                        \"pasta=8:00/al dente;10:00/soft\".
+  --steps STEPS         Run steps one after another, given as
+                        TIME[/LABEL];... or @RECIPE, e.g.
+                        \"10m/Boil;5m/Rest;25m/Bake\". Each step begins
+                        once the previous one is acknowledged with [a].
+                        {t} and {l} refer to the current step.
+  --auto-steps STEPS    Like --steps, but begin each step right away.
+  --pomodoro WORK/SHORT/LONG[/COUNT]
+                        Alternate work periods and short breaks, with a
+                        long break after every COUNT (default 4) work
+                        periods, e.g. \"25m/5m/15m\". Completed periods
+                        are summed up on exit.
+  -t, --timer NAME      Add another timer called NAME. Following alarms
+                        and countdown targets belong to it. Timers after
+                        the first wait to be started with [SPACE]. Press
+                        [TAB] to switch between them.
 
-SIGNALS: <SIGUSR1> Reset clock.
-         <SIGUSR2> Pause or continue."
+COMMANDS: add ALARM[/LABEL]  Add alarm.
+          remove LABEL|TIME  Remove alarm.
+          acknowledge        Acknowledge alarms.
+          lap                Record lap.
+          pause, resume      Pause or continue clock.
+          reset              Reset clock.
+          shift [+|-]TIME    Set clock forward or back.
+          status             Print \"running\" or \"paused\", elapsed
+                             seconds, seconds until the next alarm
+                             and its label.
+          Commands apply to the focused timer unless --timer is given.
+
+CONFIG:  Defaults are read from $XDG_CONFIG_HOME/kitchentimer/config,
+         one entry per line: \"font NAME\", \"font-file PATH\",
+         \"figlet-font PATH\", \"quit\", \"tenths\", \"scale\", \"title\",
+         \"notify BACKEND\", \"exec COMMAND\", \"nag TIME\", \"snooze TIME\",
+         \"alarm ALARM\", \"colors COLOR...\" and \"bind KEY ACTION\".
+         Alarms are set if none are given. Command line options take
+         precedence.
+
+SIGNALS: <SIGUSR1> Reset focused clock.
+         <SIGUSR2> Pause or continue focused clock."
     );
     pub const MENUBAR: &str =
-        "[0-9] Add alarm  [d] Delete alarm  [SPACE] Pause  [r] Reset  [c] Clear color  [q] Quit";
+        "[0-9] Add alarm  [d] Delete alarm  [SPACE] Pause  [r] Reset  [c] Clear color  [ENTER] Lap  [q] Quit";
     pub const MENUBAR_SHORT: &str =
-        "[0-9] Add  [d] Delete  [SPACE] Pause  [r] Reset  [c] Clear  [q] Quit";
+        "[0-9] Add  [d] Delete  [SPACE] Pause  [r] Reset  [ENTER] Lap  [q] Quit";
     pub const MENUBAR_INS: &str =
-        "Format: HH:MM:SS/LABEL  [ENTER] Accept  [ESC] Cancel  [CTR-C] Quit";
+        "Format: HH:MM:SS or 1h30m/LABEL  [ENTER] Accept  [ESC] Cancel  [CTR-C] Quit";
+    pub const MENUBAR_PENDING: &str =
+        "[a] Acknowledge  [s] Snooze  [0-9] Add alarm  [SPACE] Pause  [r] Reset  [q] Quit";
+    pub const MENUBAR_SELECTED: &str =
+        "[j]/[k] Select  [e] Edit  [l] Label  [d] Delete  [ESC] Deselect  [q] Quit";
     pub const MENUBAR_PAUSED: &str = "[SPACE] Continue  [r] Reset  [UP]/[DOWN] Set clock";
+    pub const TOO_SMALL: &str = "Window too small.";
 }
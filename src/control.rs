@@ -0,0 +1,179 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Control socket. Every line received is a command which is answered by a
+// single line starting with "ok" or "error".
+
+use crate::Event;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::{env, fs, thread};
+
+// Command received on the control socket, to be processed by the main loop.
+pub struct Request {
+    // Timer addressed. The focused timer if none.
+    pub timer: Option<String>,
+    pub command: String,
+    pub reply: mpsc::Sender<Result<String, String>>,
+}
+
+// Default location of the control socket.
+pub fn default_path() -> Option<PathBuf> {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) if !dir.is_empty() => {
+            Some(PathBuf::from(dir).join(concat!(env!("CARGO_PKG_NAME"), ".sock")))
+        }
+        _ => None,
+    }
+}
+
+// Listen on the control socket and forward commands to the main thread.
+pub fn listen(path: &Path, tx: mpsc::Sender<Event>) -> Result<(), io::Error> {
+    if path.exists() {
+        // Do not take over the socket of another running instance, but
+        // replace one left behind.
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(io::ErrorKind::AddrInUse, "Socket in use."));
+        }
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let tx = tx.clone();
+            thread::spawn(move || serve(stream, tx));
+        }
+    });
+    Ok(())
+}
+
+// Remove control socket when exiting.
+pub fn remove(path: &Path) {
+    let _ = fs::remove_file(path);
+}
+
+// Answer commands of a single connection.
+fn serve(stream: UnixStream, tx: mpsc::Sender<Event>) {
+    let mut writer = match stream.try_clone() {
+        Ok(writer) => writer,
+        Err(_) => return,
+    };
+    let mut timer = None;
+
+    for line in BufReader::new(stream).lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(_) => return,
+        };
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+
+        let reply = match command.split_once(' ') {
+            // Address commands on this connection to the given timer.
+            Some(("timer", name)) => {
+                timer = Some(name.trim().to_string());
+                Ok(String::new())
+            }
+            _ => {
+                let (reply_tx, reply_rx) = mpsc::channel();
+                let request = Request {
+                    timer: timer.clone(),
+                    command: command.to_string(),
+                    reply: reply_tx,
+                };
+                if tx.send(Event::Control(request)).is_err() {
+                    return;
+                }
+                match reply_rx.recv() {
+                    Ok(reply) => reply,
+                    Err(_) => return,
+                }
+            }
+        };
+
+        let written = match reply {
+            Ok(msg) if msg.is_empty() => writeln!(writer, "ok"),
+            Ok(msg) => writeln!(writer, "ok {}", msg),
+            Err(msg) => writeln!(writer, "error {}", msg),
+        };
+        if written.is_err() {
+            return;
+        }
+    }
+}
+
+// Send a command to a running instance. Returns the reply on success.
+pub fn send(args: impl Iterator<Item = String>) -> Result<String, String> {
+    let mut path = None;
+    let mut timer = None;
+    let mut words = Vec::new();
+    let mut iter = args;
+
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--socket" if words.is_empty() => match iter.next() {
+                Some(p) => path = Some(PathBuf::from(p)),
+                None => return Err(format!("Missing parameter to \"{}\".", arg)),
+            },
+            "--timer" if words.is_empty() => match iter.next() {
+                Some(name) => timer = Some(name),
+                None => return Err(format!("Missing parameter to \"{}\".", arg)),
+            },
+            _ => words.push(arg),
+        }
+    }
+    if words.is_empty() {
+        return Err(String::from("Missing command."));
+    }
+    let path = path
+        .or_else(default_path)
+        .ok_or("No socket given and $XDG_RUNTIME_DIR not set.")?;
+
+    let stream = UnixStream::connect(&path)
+        .map_err(|e| format!("Error connecting to {}. ({})", path.display(), e))?;
+    let mut writer = stream.try_clone().map_err(|e| e.to_string())?;
+    let mut reader = BufReader::new(stream);
+
+    if let Some(name) = timer {
+        request(&mut writer, &mut reader, &format!("timer {}", name))?;
+    }
+    request(&mut writer, &mut reader, &words.join(" "))
+}
+
+// Send a single command and wait for the reply.
+fn request(
+    writer: &mut UnixStream,
+    reader: &mut BufReader<UnixStream>,
+    command: &str,
+) -> Result<String, String> {
+    writeln!(writer, "{}", command).map_err(|e| e.to_string())?;
+
+    let mut reply = String::new();
+    reader.read_line(&mut reply).map_err(|e| e.to_string())?;
+    let reply = reply.trim_end();
+
+    match reply.split_once(' ').unwrap_or((reply, "")) {
+        ("ok", msg) => Ok(msg.to_string()),
+        ("error", msg) => Err(msg.to_string()),
+        _ => Err(String::from("Unexpected reply.")),
+    }
+}
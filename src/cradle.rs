@@ -15,12 +15,16 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::events::{Queue, Value};
+use crate::utils::format_duration;
 use std::process::{self, Command, Stdio};
 
 // Manages spawned child processes.
 pub struct Cradle {
     commands: Vec<Vec<String>>,
     children: Vec<process::Child>,
+    // Events not written yet.
+    pub events: Queue,
 }
 
 impl Drop for Cradle {
@@ -46,6 +50,7 @@ impl Cradle {
         Cradle {
             commands: Vec::new(),
             children: Vec::new(),
+            events: Queue::new(),
         }
     }
 
@@ -56,27 +61,18 @@ impl Cradle {
         self.children.reserve(self.commands.len());
     }
 
-    pub fn run_all(&mut self, time: u32, label: &String) {
+    pub fn run_all(&mut self, time: u32, label: &str) {
         // Do nothing if there are still running child processes.
         if !self.children.is_empty() { return; }
 
-        let time = if time < 3600 {
-            format!("{:02}:{:02}", time / 60, time % 60)
-        } else {
-            format!(
-                "{:02}:{:02}:{:02}",
-                time / 3600,
-                (time / 60) % 60,
-                time % 60
-            )
-        };
+        let time = format_duration(time);
 
         for command in self.commands.iter() {
             let mut args: Vec<String> = Vec::new();
             // Build vector of command line arguments. Replace every occurrence of
             // "{t}" and "{l}".
             for s in command.iter().skip(1) {
-                args.push(s.replace("{t}", &time).replace("{l}", &label));
+                args.push(s.replace("{t}", &time).replace("{l}", label));
             }
 
             match Command::new(&command[0])
@@ -85,7 +81,16 @@ impl Cradle {
                 .stdin(Stdio::null())
                 .spawn()
             {
-                Ok(child) => self.children.push(child),
+                Ok(child) => {
+                    self.events.push(
+                        "command_spawned",
+                        &[
+                            ("command", Value::Str(&command[0])),
+                            ("pid", Value::Num(child.id() as i64)),
+                        ],
+                    );
+                    self.children.push(child);
+                }
                 Err(error) => eprintln!("Error: Could not execute command. ({})", error),
             }
         }
@@ -94,10 +99,18 @@ impl Cradle {
     pub fn tend(&mut self) {
         while let Some(mut child) = self.children.pop() {
             match child.try_wait() {
-                // Process exited successfully.
-                Ok(Some(status)) if status.success() => (),
-                // Abnormal exit.
-                Ok(Some(status)) => eprintln!("Spawned process terminated with non-zero exit status. ({})", status),
+                Ok(Some(status)) => {
+                    // Exit code is missing if terminated by a signal.
+                    let code = status.code().map_or(Value::Null, |c| Value::Num(c as i64));
+                    self.events.push(
+                        "command_exited",
+                        &[("pid", Value::Num(child.id() as i64)), ("status", code)],
+                    );
+                    // Abnormal exit.
+                    if !status.success() {
+                        eprintln!("Spawned process terminated with non-zero exit status. ({})", status);
+                    }
+                }
                 // Process is still running. Put back child and return.
                 // Leaving any other children unattended, which shouldn't be
                 // a problem, as we will not spawn any further commands, as
@@ -129,13 +142,13 @@ impl Cradle {
                 }
                 // Keep spaces when escaped or quoted.
                 ' ' if escaped || quoted => {
-                    &segment.push(' ');
+                    segment.push(' ');
                 }
                 // Otherwise end the current segment.
                 ' ' => {
                     if !&segment.is_empty() {
                         command.push(segment.clone());
-                        &segment.clear();
+                        segment.clear();
                     }
                 }
                 // Quotation marks toggle quote.
@@ -143,9 +156,9 @@ impl Cradle {
                 // Carry everything else. Escape if found escaped.
                 _ => {
                     if escaped {
-                        &segment.push('\\');
+                        segment.push('\\');
                     }
-                    &segment.push(c);
+                    segment.push(c);
                 }
             }
             escaped = false;
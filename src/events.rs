@@ -0,0 +1,124 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Event stream. Every event is written as a single line of JSON, holding the
+// UNIX time, the name of the event, the timer concerned and its fields.
+
+use crate::utils::json_escape;
+use std::fmt::Write as _;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::os::unix::io::FromRawFd;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub enum Value<'a> {
+    Num(i64),
+    Str(&'a str),
+    Bool(bool),
+    Null,
+}
+
+// Event not written yet.
+#[derive(Clone)]
+struct Event {
+    time: Duration,
+    name: &'static str,
+    // Fields rendered as JSON, each preceded by a comma.
+    fields: String,
+}
+
+// Events are queued where they happen and written by the main loop, which
+// holds the sink and knows the timer concerned.
+#[derive(Clone, Default)]
+pub struct Queue {
+    list: Vec<Event>,
+}
+
+impl Queue {
+    pub fn new() -> Queue {
+        Queue { list: Vec::new() }
+    }
+
+    // Queue event.
+    pub fn push(&mut self, name: &'static str, fields: &[(&str, Value)]) {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let mut rendered = String::new();
+        for (key, value) in fields {
+            let _ = match value {
+                Value::Num(n) => write!(rendered, ",\"{}\":{}", key, n),
+                Value::Str(s) => write!(rendered, ",\"{}\":\"{}\"", key, json_escape(s)),
+                Value::Bool(b) => write!(rendered, ",\"{}\":{}", key, b),
+                Value::Null => write!(rendered, ",\"{}\":null", key),
+            };
+        }
+        self.list.push(Event {
+            time,
+            name,
+            fields: rendered,
+        });
+    }
+
+    // Write queued events to "sink", naming the timer concerned if any.
+    // Events are dropped if there is no sink. Stops writing once the reader
+    // went away.
+    pub fn flush<W: Write>(&mut self, sink: &mut Option<W>, timer: Option<&str>) {
+        for event in self.list.drain(..) {
+            let out = match sink.as_mut() {
+                Some(out) => out,
+                None => continue,
+            };
+            let mut line = format!(
+                "{{\"time\":{}.{:03},\"event\":\"{}\"",
+                event.time.as_secs(),
+                event.time.subsec_millis(),
+                event.name
+            );
+            if let Some(timer) = timer {
+                let _ = write!(line, ",\"timer\":\"{}\"", json_escape(timer));
+            }
+            line.push_str(&event.fields);
+            line.push_str("}\n");
+
+            if out.write_all(line.as_bytes()).is_err() {
+                *sink = None;
+            }
+        }
+    }
+}
+
+// Open file to append events to.
+pub fn open(path: &str) -> Result<File, String> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Error opening \"{}\". ({})", path, e))
+}
+
+// Use an inherited file descriptor for events.
+pub fn open_fd(fd: &str) -> Result<File, String> {
+    let fd: i32 = fd
+        .parse()
+        .map_err(|_| format!("Invalid file descriptor \"{}\".", fd))?;
+    // Make sure the descriptor is open before taking ownership.
+    if fd < 0 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        return Err(format!("File descriptor {} is not open.", fd));
+    }
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
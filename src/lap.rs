@@ -0,0 +1,132 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Lap times taken with the stopwatch, shown below the alarms.
+
+use crate::timer::Timer;
+use crate::utils::format_precise;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+use termion::cursor;
+use termion::raw::RawTerminal;
+
+#[derive(Clone)]
+pub struct Laps {
+    list: Vec<Duration>,
+}
+
+impl Laps {
+    pub fn new() -> Laps {
+        Laps { list: Vec::new() }
+    }
+
+    // Record lap at clock time "time". Returns the lap number.
+    pub fn record(&mut self, time: Duration) -> usize {
+        self.list.push(time);
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.list.clear();
+    }
+
+    // Write laps to the session file.
+    pub fn save<W: Write>(&self, out: &mut W) -> Result<(), std::io::Error> {
+        for time in &self.list {
+            writeln!(out, "lap {}", time.as_millis())?;
+        }
+        Ok(())
+    }
+
+    // Restore lap from its state in the session file.
+    pub fn restore(&mut self, state: &str) -> Result<(), &'static str> {
+        let millis = state.parse().map_err(|_| "Malformed lap.")?;
+        self.list.push(Duration::from_millis(millis));
+        Ok(())
+    }
+
+    // Lap number, clock time and time passed since the previous lap.
+    fn splits(&self) -> impl Iterator<Item = (usize, Duration, Duration)> + '_ {
+        self.list.iter().enumerate().map(move |(i, time)| {
+            let previous = if i > 0 { self.list[i - 1] } else { Duration::ZERO };
+            (i + 1, *time, time.saturating_sub(previous))
+        })
+    }
+
+    fn line(number: usize, time: Duration, split: Duration) -> String {
+        format!("{:>2}. {} +{}", number, format_precise(time), format_precise(split))
+    }
+
+    // Width of the widest lap.
+    pub fn width(&self) -> u16 {
+        self.splits()
+            .map(|(n, time, split)| Laps::line(n, time, split).len() as u16)
+            .max()
+            .unwrap_or(0)
+    }
+
+    // Draw the latest laps fitting into "lines" lines.
+    pub fn draw<W: Write>(
+        &self,
+        stdout: &mut RawTerminal<W>,
+        col: u16,
+        line: u16,
+        lines: u16,
+    ) -> Result<(), std::io::Error> {
+        let skip = self.list.len().saturating_sub(lines as usize);
+        for (i, (n, time, split)) in self.splits().skip(skip).enumerate() {
+            write!(
+                stdout,
+                "{}{}",
+                cursor::Goto(col, line + i as u16),
+                Laps::line(n, time, split)
+            )?;
+        }
+        Ok(())
+    }
+
+    // Write laps as CSV lines: timer, lap, time and split in seconds.
+    pub fn write_csv<W: Write>(&self, out: &mut W, timer: &str) -> Result<(), std::io::Error> {
+        for (n, time, split) in self.splits() {
+            writeln!(
+                out,
+                "\"{}\",{},{:.3},{:.3}",
+                timer.replace('"', "\"\""),
+                n,
+                time.as_secs_f64(),
+                split.as_secs_f64()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// Write laps of all timers to "path" as CSV.
+pub fn export(path: &Path, timers: &[Timer]) -> Result<(), std::io::Error> {
+    let mut file = BufWriter::new(File::create(path)?);
+    writeln!(file, "timer,lap,time,split")?;
+    for timer in timers {
+        timer.alarm_roster.laps.write_csv(&mut file, &timer.name)?;
+    }
+    file.flush()
+}
@@ -15,6 +15,7 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
+use crate::clock::font::{self, Font};
 use crate::clock::Clock;
 
 pub struct Position {
@@ -37,12 +38,36 @@ pub struct Layout {
     clock_width: u16,
     clock_height: u16,
     digit_width: u16,
+    colon_width: u16,
+    dot_width: u16,
+    // Font to draw the clock with. Falls back to a smaller one if the clock's
+    // own font does not fit.
+    pub font: &'static Font,
+    // Fonts enlarged to fill the window with the original and the factor.
+    // Kept for reuse.
+    scaled: Vec<(&'static Font, u16, &'static Font)>,
+    // Draw the clock as a single line of text followed by the next alarm,
+    // used if not even the smaller font fits.
+    pub compact: bool,
+    // Not even the single line fits, show a notice instead.
+    pub too_small: bool,
+    // Part of the screen assigned when showing multiple timers.
+    slot: u16,
+    slots: u16,
+    pub header: Position,
     pub clock_sec: Position,
     pub clock_colon0: Position,
     pub clock_min: Position,
     pub clock_colon1: Position,
     pub clock_hr: Position,
     pub clock_days: Position,
+    // Tenths of a second and the dot separating them from seconds.
+    pub clock_dot: Position,
+    pub clock_tenth: Position,
+    // Next alarm and its countdown, right of the compact clock.
+    pub next_alarm: Position,
+    // Center of the line showing the current step.
+    pub step: Position,
     pub roster: Position,
     pub roster_width: u16,
     pub roster_height: u16,
@@ -59,12 +84,25 @@ impl Layout {
             clock_width: 0,
             clock_height: 0,
             digit_width: 0,
+            colon_width: 0,
+            dot_width: 0,
+            font: &font::NORMAL,
+            scaled: Vec::new(),
+            compact: false,
+            too_small: false,
+            slot: 0,
+            slots: 1,
+            header: Position::new(),
             clock_sec: Position::new(),
             clock_colon0: Position::new(),
             clock_min: Position::new(),
             clock_colon1: Position::new(),
             clock_hr: Position::new(),
             clock_days: Position::new(),
+            clock_dot: Position::new(),
+            clock_tenth: Position::new(),
+            next_alarm: Position::new(),
+            step: Position::new(),
             roster: Position { col: 1, line: 3 },
             roster_width: 0,
             roster_height: 0,
@@ -79,10 +117,8 @@ impl Layout {
             let (width, height) = termion::terminal_size()?;
             self.width = width;
             self.height = height;
-            self.clock_width = clock.get_width();
-            self.clock_height = clock.font.height;
-            self.digit_width = clock.font.width;
-            self.compute(clock.elapsed >= 3600);
+            self.select_font(clock, clock.shows_hours());
+            self.compute(clock.shows_hours(), clock.tenths);
             Ok(true)
         } else {
             Ok(false)
@@ -97,11 +133,86 @@ impl Layout {
     pub fn test_update(&mut self, clock: &Clock, width: u16, height: u16, roster_width: u16) {
         self.width = width;
         self.height = height;
-        self.clock_width = clock.get_width();
-        self.clock_height = clock.font.height;
-        self.digit_width = clock.font.width;
         self.roster_width = roster_width;
-        self.compute(false);
+        self.select_font(clock, clock.shows_hours());
+        self.compute(clock.shows_hours(), clock.tenths);
+    }
+
+    // Assign part of the screen to this layout. "slot" counts from 0.
+    pub fn set_slot(&mut self, slot: u16, slots: u16) {
+        self.slot = slot;
+        self.slots = slots;
+        self.force_recalc = true;
+    }
+
+    // First line and number of lines of our part of the screen.
+    fn region(&self) -> (u16, u16) {
+        if self.slots > 1 {
+            // Leave room for menu bar and input buffer.
+            let lines = self.height.saturating_sub(2) / self.slots;
+            (2 + self.slot * lines, lines)
+        } else {
+            (1, self.height)
+        }
+    }
+
+    // Where to tell about a slot too small for the clock. None if the slot
+    // has no line at all.
+    pub fn notice(&self) -> Option<Position> {
+        let (top, lines) = self.region();
+        if lines == 0 || top > self.height {
+            return None;
+        }
+        Some(Position { line: top, col: 1 })
+    }
+
+    // Use the smaller font if the preferred one does not fit in our region.
+    // When scaling, use the largest multiple of the preferred font that fits
+    // instead. Draw a single line of text if not even the smaller font does.
+    fn select_font(&mut self, clock: &Clock, display_hours: bool) {
+        let (_, lines) = self.region();
+        let preferred = clock.font;
+        let fits = |font: &Font, factor: u16, room_width: u16, room_height: u16| {
+            font.height * factor <= room_height
+                && clock_width(font, factor, display_hours, clock.tenths) <= room_width
+        };
+        let font = if clock.scale {
+            // Leave room for the menu bar, current step, days and input
+            // buffer.
+            let room_height = if self.slots > 1 {
+                lines.saturating_sub(2)
+            } else {
+                lines.saturating_sub(6)
+            };
+            let room_width = self.width.saturating_sub(self.roster_width + 2);
+            (1..=room_height / preferred.height)
+                .rev()
+                .find(|factor| fits(preferred, *factor, room_width, room_height))
+                .map(|factor| self.scaled(preferred, factor))
+                .or_else(|| Some(&font::SMALL).filter(|small| fits(small, 1, room_width, room_height)))
+        } else {
+            // Multiple timers need a spare line above and below the
+            // preferred font.
+            let spare = if self.slots > 1 { 2 } else { 0 };
+            Some(preferred)
+                .filter(|font| fits(font, 1, self.width, lines.saturating_sub(spare)))
+                .or_else(|| Some(&font::SMALL).filter(|small| fits(small, 1, self.width, lines)))
+                .filter(|_| lines >= 2)
+        };
+        self.compact = font.is_none();
+        self.font = font.unwrap_or(&font::SMALL);
+        if self.compact {
+            // [-][HH:]MM:SS[.T]
+            self.clock_width = 1 + if display_hours { 8 } else { 5 } + if clock.tenths { 2 } else { 0 };
+            self.clock_height = 1;
+        } else {
+            self.clock_width = clock_width(self.font, 1, display_hours, clock.tenths);
+            self.clock_height = self.font.height;
+        }
+        self.digit_width = self.font.width;
+        self.colon_width = self.font.colon_width;
+        self.dot_width = self.font.dot_width();
+        self.too_small = self.width < self.clock_width || lines < self.clock_height;
     }
 
     pub fn can_hold(&self, other: &str) -> bool {
@@ -109,56 +220,119 @@ impl Layout {
         self.width >= other.len() as u16
     }
 
+    // "font" enlarged by "factor", generated once.
+    fn scaled(&mut self, font: &'static Font, factor: u16) -> &'static Font {
+        if factor <= 1 {
+            return font;
+        }
+        if let Some((_, _, scaled)) = self
+            .scaled
+            .iter()
+            .find(|(original, f, _)| std::ptr::eq(*original, font) && *f == factor)
+        {
+            return scaled;
+        }
+        let scaled: &'static Font = Box::leak(Box::new(font::scale(font, factor)));
+        self.scaled.push((font, factor, scaled));
+        scaled
+    }
+
     // Compute the position of various elements based on the size of the
     // terminal.
-    fn compute(&mut self, display_hours: bool) {
-        // Prevent integer overflow at very low screen sizes.
-        if self.width < self.clock_width || self.height < self.clock_height {
+    fn compute(&mut self, display_hours: bool, tenths: bool) {
+        let (top, lines) = self.region();
+        // Prevent integer overflow at very low screen sizes. Positions are
+        // not used in this case.
+        if self.too_small {
             return;
         }
 
-        let middle: u16 = self.height / 2 - 1;
+        let middle: u16 = if self.slots > 1 {
+            // Center clock in our region, below the header.
+            self.header = Position { col: 1, line: top };
+            self.roster.line = top + 1;
+            top + (lines - self.clock_height).div_ceil(2)
+        } else {
+            self.roster.line = 3;
+            // Same as height / 2 - 1 for the usual five lines.
+            (self.height + 3 - self.clock_height) / 2
+        };
 
-        if display_hours {
-            // Seconds digits.
-            self.clock_sec.col = (self.width + self.roster_width) / 2 + self.digit_width + 6;
-            // Colon separating minutes from seconds.
-            self.clock_colon0.col = (self.width + self.roster_width) / 2 + self.digit_width + 3;
+        // Make room for tenths of a second on the right.
+        let center = (self.width + self.roster_width) / 2
+            - if tenths && !self.compact { (self.digit_width + 2 + self.dot_width) / 2 } else { 0 };
+
+        if self.compact {
+            // Text starts at the position of the seconds. Keep left of the
+            // window so the next alarm fits alongside.
+            self.clock_sec.col = 1;
+        } else if display_hours {
             // Minute digits.
-            self.clock_min.col = (self.width + self.roster_width) / 2 - self.digit_width;
+            self.clock_min.col = center - self.digit_width;
+            // Colon separating minutes from seconds.
+            self.clock_colon0.col = center + self.digit_width + 3;
 
             // Colon separating hours from minutes.
             self.clock_colon1 = Position {
-                col: (self.width + self.roster_width) / 2 - (self.digit_width + 3),
+                col: center - (self.digit_width + 2 + self.colon_width),
                 line: middle,
             };
 
             // Hour digits.
             self.clock_hr = Position {
-                col: (self.width + self.roster_width) / 2 - (self.digit_width * 3 + 6),
+                col: self.clock_colon1.col - (self.digit_width * 2 + 3),
                 line: middle,
             };
         } else {
-            // Seconds digits.
-            self.clock_sec.col = (self.width + self.roster_width) / 2 + 3;
             // Colon separating minutes from seconds.
-            self.clock_colon0.col = (self.width + self.roster_width) / 2;
+            self.clock_colon0.col = center - self.colon_width / 2;
             // Minute digits.
-            self.clock_min.col = (self.width + self.roster_width) / 2 - (self.digit_width * 2 + 3);
+            self.clock_min.col = self.clock_colon0.col - (self.digit_width * 2 + 3);
+        }
+        if !self.compact {
+            // Seconds digits.
+            self.clock_sec.col = self.clock_colon0.col + self.colon_width + 2;
         }
 
         self.clock_sec.line = middle;
         self.clock_colon0.line = middle;
         self.clock_min.line = middle;
 
+        // Tenths (based on position of seconds).
+        self.clock_dot = Position {
+            line: middle,
+            col: self.clock_sec.col + self.digit_width * 2 + 2,
+        };
+        self.clock_tenth = Position {
+            line: middle,
+            col: self.clock_dot.col + self.dot_width + 1,
+        };
+
+        // Next alarm, right of the compact clock.
+        self.next_alarm = Position {
+            line: middle,
+            col: self.clock_sec.col + self.clock_width,
+        };
+
         // Days (based on position of seconds).
         self.clock_days = Position {
-            line: self.clock_sec.line + self.digit_width,
+            line: self.clock_sec.line + self.clock_height,
             col: self.clock_sec.col,
         };
 
-        // Alarm roster height.
-        self.roster_height = self.height - self.roster.line - 1;
+        // Current step, above the clock.
+        self.step = Position {
+            line: middle.saturating_sub(2).max(top),
+            col: (self.width + self.roster_width) / 2 + 1,
+        };
+
+        // Alarm roster height. Keep the last line free for the input buffer
+        // unless it is outside of our region anyway.
+        self.roster_height = if self.slots > 1 {
+            (top + lines - 1).saturating_sub(self.roster.line)
+        } else {
+            self.height.saturating_sub(self.roster.line + 1)
+        };
 
         // Input buffer.
         self.buffer = Position {
@@ -174,3 +348,22 @@ impl Layout {
         }
     }
 }
+
+// Width of the clock drawn with "font" enlarged by "factor".
+fn clock_width(font: &Font, factor: u16, display_hours: bool, tenths: bool) -> u16 {
+    let digit = font.width * factor;
+    let colon = font.colon_width * factor;
+    // Colons are surrounded by two spaces on each side.
+    let mut width = if display_hours {
+        // Hours
+        digit * 6 + 3 + 2 * (colon + 4)
+    } else {
+        // Minutes and seconds only.
+        digit * 4 + 2 + colon + 4
+    };
+    if tenths {
+        // Dot and a single digit.
+        width += digit + 2 + font.dot_width() * factor;
+    }
+    width
+}
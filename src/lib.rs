@@ -22,55 +22,127 @@ mod alarm;
 mod buffer;
 mod clock;
 mod consts;
+mod control;
 mod cradle;
+mod events;
+mod lap;
 mod layout;
+mod notify;
+mod palette;
+mod recipe;
+mod sequence;
+mod session;
+mod settings;
+mod status;
 #[cfg(test)]
 mod tests;
+mod timer;
+mod title;
 mod utils;
 
 pub use alarm::AlarmRoster;
-use alarm::Countdown;
-use buffer::Buffer;
-use clock::{font, Clock};
+use alarm::Alarm;
+use buffer::{Buffer, Mode};
+use clock::font;
 use consts::ui::*;
+use recipe::Recipes;
+use sequence::Sequence;
 use cradle::Cradle;
-use layout::Layout;
 use signal_hook::consts::signal::*;
 use signal_hook::iterator::Signals;
 use signal_hook::low_level;
+use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::io::Write;
+use std::path::PathBuf;
 use std::{env, process, thread, time};
 use std::sync::mpsc;
 use termion::event::Key;
 use termion::input::TermRead;
 use termion::raw::{IntoRawMode, RawTerminal};
 use termion::{clear, cursor, style};
+use timer::{Setup, Timer};
+
+// Input to the main loop.
+enum Event {
+    Key(Result<Key, std::io::Error>),
+    Control(control::Request),
+}
 
 pub fn run(
     mut config: Config,
-    mut alarm_roster: AlarmRoster,
+    alarm_roster: AlarmRoster,
 ) -> Result<(), std::io::Error> {
-    let mut layout = Layout::new();
-    // Initialise roster_width.
-    layout.set_roster_width(alarm_roster.width());
-    let mut clock = Clock::new(&config);
-    let mut countdown = Countdown::new();
+    let mut setups = std::mem::take(&mut config.timers);
+    setups[0].alarm_roster = alarm_roster;
+    let mut timers: Vec<Timer> = setups
+        .into_iter()
+        .enumerate()
+        .map(|(i, setup)| Timer::new(&config, i, setup))
+        .collect();
+    // Split screen between timers.
+    let slots = timers.len() as u16;
+    for (i, timer) in timers.iter_mut().enumerate() {
+        timer.layout.set_slot(i as u16, slots);
+    }
+
+    for timer in timers.iter_mut() {
+        timer.emit("started");
+    }
+
+    // Listen for commands on the control socket.
+    let (tx, rx) = mpsc::channel();
+    let socket_error = match &config.socket {
+        Some(path) => control::listen(path, tx.clone()).is_err(),
+        None => false,
+    };
+    if socket_error {
+        config.socket = None;
+    }
+
+    // Print status lines instead of running the user interface.
+    if let Some(format) = config.status {
+        if socket_error {
+            eprintln!("Control socket not available.");
+        }
+        drop(tx);
+        return status::run(config, timers, format, rx);
+    }
+
+    // Index of the timer receiving input.
+    let mut focus = 0;
     let mut buffer = Buffer::new();
+    if socket_error {
+        buffer.message("Control socket not available.");
+    }
+    // Report alarms missed while we were not running.
+    if config.resume && timers.iter().any(|t| t.alarm_roster.overdue(&t.clock)) {
+        buffer.message("Alarms went off while kitchentimer was not running.");
+    }
+    // Stop writing the session file after the first error.
+    let mut keep_session = true;
     let stdout = std::io::stdout();
     let mut stdout = stdout.lock().into_raw_mode()?;
     let mut force_redraw = true;
+    // Title last written to the terminal.
+    let mut last_title = String::new();
+    // Restores the title when dropped.
+    let mut title = if config.title {
+        Some(title::Title::push(&mut stdout)?)
+    } else {
+        None
+    };
 
     // Register signals.
-    let mut signals = Signals::new(&[
+    let mut signals = Signals::new([
         SIGTSTP, SIGCONT, SIGWINCH, SIGTERM, SIGINT, SIGUSR1, SIGUSR2,
     ])?;
 
     // Read input keys and send them back to the main thread.
     let tty = termion::get_tty()?;
-    let (tx, rx) = mpsc::channel();
     thread::spawn(move || {
         for key in tty.keys() {
-            if tx.send(key).is_err() {
+            if tx.send(Event::Key(key)).is_err() {
                 return;
             }
         }
@@ -78,65 +150,97 @@ pub fn run(
 
     // Main loop entry.
     'main: loop {
-        // Update elapsed time.
-        let elapsed = if clock.paused {
-            clock.elapsed
-        } else {
-            // Should never overflow as we reestablish a new "start"
-            // instant every 24 hours.
-            clock.start.elapsed().as_secs() as u32
-        };
+        // Check on last spawned child process prior to processing the
+        // alarm rosters and possibly spawning a new set.
+        config.commands.tend();
 
-        // Conditional inner loop. Runs once every second or when explicitly
-        // requested.
-        if elapsed != clock.elapsed || force_redraw {
-            // Update clock. Advance one day after 24 hours.
-            if elapsed < 24 * 60 * 60 {
-                clock.elapsed = elapsed;
-            } else {
-                clock.next_day();
-                // "clock.elapsed" set by "clock.next_day()".
-                alarm_roster.reset_all();
-                layout.schedule_recalc();
-            }
+        // Timers to redraw.
+        let mut ticked = vec![false; timers.len()];
+        // Timers showing a new tenth of a second. Only the clock needs to be
+        // redrawn.
+        let mut tenths = vec![false; timers.len()];
+        // Whether an alarm was raised on any of the timers.
+        let mut raised = false;
+
+        for (i, timer) in timers.iter_mut().enumerate() {
+            // Runs once every second or when explicitly requested.
+            // Keep checking on alarms set to a time of day while paused and
+            // whenever one of them is due.
+            let tenth = timer.clock.tenth();
+            ticked[i] = timer.tick()
+                || force_redraw
+                || timer.clock.paused && timer.alarm_roster.time_of_day_pending()
+                || timer.alarm_roster.until_time_of_day() == Some(time::Duration::ZERO);
+            tenths[i] = timer.clock.tenth() != tenth;
+
+            let Timer {
+                clock,
+                alarm_roster,
+                countdown,
+                layout,
+                ..
+            } = timer;
 
             // Update window size information and calculate the clock position.
             // Also enforce recalculation of layout if we start displaying
             // hours.
-            match layout.update(&clock, clock.elapsed == 3600) {
+            match layout.update(clock, ticked[i] && clock.hour_barrier()) {
                 Ok(true) => force_redraw = true,
                 Ok(false) => (),
                 Err(e) => return Err(e),
             }
 
-            // Check on last spawned child process prior to processing the
-            // alarm roster and possibly spawning a new set.
-            config.commands.tend();
+            if !ticked[i] {
+                continue;
+            }
 
             // Check for exceeded alarms.
             if let Some(alarm) =
-                alarm_roster.check(&mut clock, &layout, &mut countdown, force_redraw)
+                alarm_roster.check(clock, layout, countdown, &mut force_redraw)
             {
-                // Do not react to exceeded alarms if the clock is paused.
-                if !clock.paused {
+                // Do not react to exceeded alarms if the clock is paused,
+                // unless they are set to a time of day.
+                if !clock.paused || alarm.is_time_of_day() {
                     force_redraw = true;
+                    raised = true;
+                    alert(&mut stdout, &mut config.commands, config.notify, &alarm)?;
 
-                    // Write ASCII bell code.
-                    write!(stdout, "{}", 0x07 as char)?;
-
-                    // Run commands.
-                    config.commands.run_all(alarm.time, &alarm.label);
+                    // Notes of repeating alarms may have changed in size.
+                    layout.set_roster_width(alarm_roster.width());
+                }
+            }
 
-                    // Quit if configured.
-                    if config.quit && alarm_roster.idle() {
-                        break;
-                    };
+            // Notify again about alarms not acknowledged yet.
+            if config.nag > 0 && !clock.paused {
+                if let Some(alarm) = alarm_roster.nag(config.nag) {
+                    alert(&mut stdout, &mut config.commands, config.notify, &alarm)?;
                 }
             }
+            clock.flash = alarm_roster.pending();
 
-            // Clear the window and redraw menu bar, alarm roster and buffer if
-            // requested.
+            // Begin next step of the sequence.
+            if timer.advance() {
+                force_redraw = true;
+            }
+        }
+
+        write_events(&mut config, &mut timers);
+
+        // Quit if configured and the last alarm has been raised.
+        if raised && config.quit && timers.iter().all(|timer| timer.alarm_roster.idle()) {
+            break;
+        }
+
+        if force_redraw || ticked.contains(&true) || tenths.contains(&true) {
+            // Clear the window and redraw menu bar, alarm rosters and buffer
+            // if requested.
             if force_redraw {
+                let Timer {
+                    clock,
+                    alarm_roster,
+                    layout,
+                    ..
+                } = &timers[focus];
                 // Write menu at the top.
                 write!(
                     stdout,
@@ -147,6 +251,14 @@ pub fn run(
                     // all if necessary.
                     match buffer.visible {
                         _ if clock.paused && layout.can_hold(MENUBAR_PAUSED) => MENUBAR_PAUSED,
+                        false if alarm_roster.pending() && layout.can_hold(MENUBAR_PENDING) => {
+                            MENUBAR_PENDING
+                        }
+                        false if alarm_roster.selected().is_some()
+                            && layout.can_hold(MENUBAR_SELECTED) =>
+                        {
+                            MENUBAR_SELECTED
+                        }
                         true if layout.can_hold(MENUBAR_INS) => MENUBAR_INS,
                         false if layout.can_hold(MENUBAR) => MENUBAR,
                         false if layout.can_hold(MENUBAR_SHORT) => MENUBAR_SHORT,
@@ -159,18 +271,73 @@ pub fn run(
                     style::NoFaint
                 )?;
 
-                // Redraw list of alarms.
-                alarm_roster.draw(&mut stdout, &mut layout)?;
+                for (i, timer) in timers.iter_mut().enumerate() {
+                    // Tell about a slot too small to hold even a single line.
+                    if timer.layout.too_small {
+                        if let Some(pos) = timer.layout.notice() {
+                            let width = timer.layout.width as usize;
+                            write!(
+                                stdout,
+                                "{}{}",
+                                cursor::Goto(pos.col, pos.line),
+                                TOO_SMALL.get(..width).unwrap_or(TOO_SMALL),
+                            )?;
+                        }
+                        continue;
+                    }
+                    // Name timers if there is more than one.
+                    if slots > 1 {
+                        timer.draw_header(&mut stdout, i == focus, &config.palette)?;
+                    }
+                    // The compact layout shows the next alarm alongside the
+                    // clock instead.
+                    if timer.layout.compact {
+                        continue;
+                    }
+
+                    // Redraw list of alarms.
+                    timer.alarm_roster.draw(&mut stdout, &mut timer.layout, &config.palette)?;
+
+                    // Name current step.
+                    timer.draw_step(&mut stdout)?;
+                }
 
                 // Redraw buffer.
-                buffer.draw(&mut stdout, &mut layout)?;
+                buffer.draw(&mut stdout, &mut timers[focus].layout)?;
+
+                // Keep session file up to date.
+                if keep_session && session::save(&timers).is_err() {
+                    keep_session = false;
+                    buffer.message("Error writing session file.");
+                }
             }
 
-            clock.draw(&mut stdout, &layout, force_redraw)?;
+            for (i, timer) in timers.iter().enumerate() {
+                // The input buffer takes the only line of a one-line window.
+                if timer.layout.too_small
+                    || timer.layout.compact && timer.layout.height == 1 && buffer.visible
+                {
+                    continue;
+                }
+                if force_redraw || ticked[i] || tenths[i] {
+                    timer.clock.draw(&mut stdout, &timer.layout, &config.palette, force_redraw)?;
+
+                    // Display countdown.
+                    if timer.layout.compact {
+                        timer.draw_next_alarm(&mut stdout)?;
+                    } else if timer.countdown.value > 0 {
+                        timer.countdown.draw(&mut stdout)?;
+                    }
+                }
+            }
 
-            // Display countdown.
-            if countdown.value > 0 {
-                countdown.draw(&mut stdout)?;
+            // Update window title if it changed.
+            if config.title {
+                let text = title::text(&timers);
+                if text != last_title {
+                    title::set(&mut stdout, &text)?;
+                    last_title = text;
+                }
             }
 
             // End of conditional inner loop.
@@ -181,12 +348,12 @@ pub fn run(
 
         // Update buffer whenever the cursor should be visible.
         if buffer.visible {
-            buffer.draw(&mut stdout, &mut layout)?;
+            buffer.draw(&mut stdout, &mut timers[focus].layout)?;
             stdout.flush()?;
         }
 
         // Process input.
-        match rx.recv_timeout(time::Duration::from_millis(250)) {
+        match rx.recv_timeout(timeout(&timers)) {
             // Timeout. Expected.
             Err(mpsc::RecvTimeoutError::Timeout) => (),
             // Disconnect.
@@ -194,22 +361,57 @@ pub fn run(
                 eprintln!("Unexpected end of input thread.");
                 break;
             }
-            Ok(key) => {
-                match key.expect("Error reading input") {
-                    // Enter.
-                    Key::Char('\n') => {
-                        if !buffer.is_empty() {
-                            if let Err(e) = alarm_roster.add(buffer.read()) {
-                                // Error while processing input buffer.
-                                buffer.message(e);
-                            } else {
-                                // Input buffer processed without error.
-                                layout.set_roster_width(alarm_roster.width());
-                            }
-                            buffer.clear();
-                            buffer.visible = false;
-                            force_redraw = true;
+            // Command received on the control socket.
+            Ok(Event::Control(request)) => {
+                let reply = match &request.timer {
+                    Some(name) => timers
+                        .iter_mut()
+                        .find(|timer| &timer.name == name)
+                        .ok_or("No such timer."),
+                    None => Ok(&mut timers[focus]),
+                }
+                .and_then(|timer| handle_command(timer, &request.command, &config.recipes));
+                // Ignore clients not waiting for the reply.
+                let _ = request.reply.send(reply.map_err(String::from));
+                force_redraw = true;
+            }
+            Ok(Event::Key(key)) => {
+                let key = key.expect("Error reading input");
+                // Translate keys bound in the config file.
+                let key = match config.keys.get(&key) {
+                    Some(action) if !buffer.visible => *action,
+                    _ => key,
+                };
+                let timer = &mut timers[focus];
+                match key {
+                    // Enter. Labels may be empty.
+                    Key::Char('\n') if buffer.visible => {
+                        let result = match buffer.mode {
+                            Mode::Add => config.recipes.add(&mut timer.alarm_roster, buffer.read()),
+                            Mode::Edit => timer.alarm_roster.edit(buffer.read()),
+                            Mode::Label => timer.alarm_roster.relabel(buffer.read()),
+                        };
+                        if let Err(e) = result {
+                            // Error while processing input buffer.
+                            buffer.message(e);
+                        } else {
+                            // Input buffer processed without error.
+                            timer.layout.set_roster_width(timer.alarm_roster.width());
+                            timer.countdown.reset();
                         }
+                        buffer.clear();
+                        buffer.visible = false;
+                        force_redraw = true;
+                    }
+                    // Record lap on [ENTER].
+                    Key::Char('\n') => {
+                        timer.lap();
+                        force_redraw = true;
+                    }
+                    // Escape clears selection if the input buffer is hidden.
+                    Key::Esc if !buffer.visible && timer.alarm_roster.selected().is_some() => {
+                        timer.alarm_roster.unselect();
+                        force_redraw = true;
                     }
                     // Escape and ^U clear input buffer.
                     Key::Esc | Key::Ctrl('u') => {
@@ -220,7 +422,9 @@ pub fn run(
                     // ^W removes last word.
                     Key::Ctrl('w') => {
                         buffer.strip_word();
-                        if buffer.is_empty() {
+                        // Labels may be emptied without closing the buffer.
+                        if buffer.is_empty() && buffer.mode == Mode::Add {
+                            buffer.clear();
                             buffer.visible = false;
                             force_redraw = true;
                         }
@@ -229,81 +433,174 @@ pub fn run(
                     Key::Backspace => {
                         // Delete last char in buffer.
                         buffer.strip_char();
-                        if buffer.is_empty() {
+                        // Labels may be emptied without closing the buffer.
+                        if buffer.is_empty() && buffer.mode == Mode::Add {
+                            buffer.clear();
                             buffer.visible = false;
                             force_redraw = true;
                         }
                     }
-                    // Set clock.
-                    Key::Up if clock.paused => {
-                        clock.shift(10);
-                        // We would very likely not detect us passing the hour
-                        // barrier and would panic when trying to draw hours
-                        // without position if we do not schedule a recalculation
-                        // here.
-                        layout.schedule_recalc();
+                    // Switch focus to next or previous timer on [TAB] and
+                    // [SHIFT]+[TAB].
+                    Key::Char('\t') if !buffer.visible => {
+                        focus = (focus + 1) % timers.len();
                         force_redraw = true;
                     }
-                    Key::Down if clock.paused => {
-                        clock.shift(-10);
-                        alarm_roster.time_travel(&mut clock);
-                        layout.schedule_recalc();
+                    Key::BackTab if !buffer.visible => {
+                        focus = (focus + timers.len() - 1) % timers.len();
                         force_redraw = true;
                     }
+                    // Set clock while paused. Shift in the opposite direction when
+                    // counting down, so [UP] always increases the displayed
+                    // value.
+                    Key::Up | Key::Down if timer.clock.paused => {
+                        let up = if timer.clock.count_down { -10 } else { 10 };
+                        timer.shift(if key == Key::Up { up } else { -up });
+                        force_redraw = true;
+                    }
+                    // Move selection in alarm roster.
+                    Key::Up | Key::Down if !buffer.visible => {
+                        let moved = timer.alarm_roster.select(&timer.layout, key == Key::Down);
+                        if moved {
+                            timer.countdown.reset();
+                            force_redraw = true;
+                        }
+                    }
                     // Scroll alarm roster.
                     Key::PageUp => {
-                        alarm_roster.scroll_up(&layout);
+                        timer.alarm_roster.scroll_up(&timer.layout);
                         force_redraw = true;
                     }
                     Key::PageDown => {
-                        alarm_roster.scroll_down(&layout);
+                        timer.alarm_roster.scroll_down(&timer.layout);
                         force_redraw = true;
                     }
                     // Forward every char if in insert mode.
                     Key::Char(c) if buffer.visible => buffer.push(c),
                     // Reset clock on 'r'.
                     Key::Char('r') => {
-                        clock.reset();
-                        alarm_roster.reset_all();
-                        layout.schedule_recalc();
+                        timer.reset();
                         force_redraw = true;
                     }
                     // (Un-)Pause on space.
                     Key::Char(' ') => {
-                        clock.toggle();
+                        timer.toggle();
+                        force_redraw = true;
+                    }
+                    // Toggle countdown on 't'. Count down to the last alarm if
+                    // no target was given.
+                    Key::Char('t') => {
+                        if timer.target.is_none() && !timer.clock.count_down {
+                            timer.clock.target = timer.alarm_roster.last_time();
+                        }
+                        if !timer.clock.toggle_direction() {
+                            buffer.message("No alarm to count down to.");
+                        }
+                        timer.layout.schedule_recalc();
                         force_redraw = true;
                     }
+                    // Acknowledge alarms on 'a'.
+                    Key::Char('a') => {
+                        let acknowledged = timer.alarm_roster.acknowledge();
+                        if acknowledged {
+                            timer.clock.flash = false;
+                            force_redraw = true;
+                        }
+                    }
+                    // Snooze alarms on 's'.
+                    Key::Char('s') => {
+                        let snoozed = timer.alarm_roster.snooze(&timer.clock, config.snooze);
+                        if snoozed {
+                            timer.clock.flash = false;
+                            timer.layout.set_roster_width(timer.alarm_roster.width());
+                            timer.countdown.reset();
+                            force_redraw = true;
+                        }
+                    }
                     // Clear clock color on 'c'.
                     Key::Char('c') => {
-                        clock.color_index = None;
+                        timer.clock.color_index = None;
+                        timer.clock.step_color = None;
                         force_redraw = true;
                     }
-                    // Delete last alarm on 'd'.
+                    // Move selection on 'j' and 'k'.
+                    Key::Char('j') | Key::Char('k') => {
+                        let moved = timer.alarm_roster.select(&timer.layout, key == Key::Char('j'));
+                        if moved {
+                            timer.countdown.reset();
+                            force_redraw = true;
+                        }
+                    }
+                    // Edit selected alarm on 'e'.
+                    Key::Char('e') => {
+                        if let Some(alarm) = timer.alarm_roster.selection() {
+                            let content = format!("{}/{}", alarm.spec(), alarm.label);
+                            buffer.open(Mode::Edit, &content);
+                            force_redraw = true;
+                        }
+                    }
+                    // Change label of selected alarm on 'l'.
+                    Key::Char('l') => {
+                        if let Some(alarm) = timer.alarm_roster.selection() {
+                            buffer.open(Mode::Label, &alarm.label);
+                            force_redraw = true;
+                        }
+                    }
+                    // Delete selected or last alarm on 'd'.
                     Key::Char('d') => {
-                        if alarm_roster.pop().is_some() {
-                            // If we remove the last alarm we have to reset "countdown"
-                            // manually. It is safe to do it anyway.
-                            layout.set_roster_width(alarm_roster.width());
-                            countdown.reset();
+                        let deleted = timer.alarm_roster.delete(&timer.layout);
+                        if deleted.is_some() {
+                            // If we remove the last alarm we have to reset
+                            // "countdown" manually. It is safe to do it anyway.
+                            timer.layout.set_roster_width(timer.alarm_roster.width());
+                            timer.countdown.reset();
                             force_redraw = true;
                         }
                     }
+                    // Switch to the next font on 'f'.
+                    Key::Char('f') => {
+                        let (next, broken) = config.fonts.next(timer.clock.font);
+                        for timer in timers.iter_mut() {
+                            timer.clock.font = next;
+                            timer.layout.schedule_recalc();
+                        }
+                        buffer.message(if broken { "Skipped broken font file." } else { next.name });
+                        force_redraw = true;
+                    }
+                    // Export laps on 'w'.
+                    Key::Char('w') => {
+                        match &config.laps {
+                            Some(path) => match lap::export(path, &timers) {
+                                Ok(()) => buffer.message("Laps exported."),
+                                Err(_) => buffer.message("Error exporting laps."),
+                            },
+                            None => buffer.message("Use --laps FILE to export laps."),
+                        }
+                        force_redraw = true;
+                    }
                     // Exit on q and ^C.
                     Key::Char('q') | Key::Ctrl('c') => break,
-                    // Exit immediately on ^\.
-                    Key::Ctrl('4') => process::exit(1),
+                    // Exit immediately on ^\. Leaves title and terminal
+                    // mode as we found them.
+                    Key::Ctrl('4') => {
+                        drop(title);
+                        drop(stdout);
+                        process::exit(1);
+                    }
                     // Force redraw on ^R.
                     Key::Ctrl('r') => force_redraw = true,
                     // Suspend an ^Z.
                     Key::Ctrl('z') => {
+                        // Restore the title while suspended. SIGCONT saves
+                        // it again.
+                        title = None;
                         suspend(&mut stdout)?;
                         force_redraw = true;
                     }
-                    Key::Char(c) => {
-                        if c.is_ascii_digit() {
-                            buffer.push(c);
-                            buffer.visible = true;
-                        }
+                    // Start entering an alarm time on any digit, "@" or "*".
+                    Key::Char(c) if c.is_ascii_digit() || c == '@' || c == '*' => {
+                        buffer.push(c);
+                        buffer.visible = true;
                     }
                     // Any other key.
                     _ => (),
@@ -315,26 +612,37 @@ pub fn run(
         for signal in signals.pending() {
             match signal {
                 // Suspend execution on SIGTSTP.
-                SIGTSTP => suspend(&mut stdout)?,
+                SIGTSTP => {
+                    title = None;
+                    suspend(&mut stdout)?
+                }
                 // Continuing after SIGTSTP or SIGSTOP.
                 SIGCONT => {
                     restore_after_suspend(&mut stdout)?;
-                    layout.schedule_recalc();
+                    if config.title {
+                        title = Some(title::Title::push(&mut stdout)?);
+                        last_title.clear();
+                    }
+                    for timer in timers.iter_mut() {
+                        timer.layout.schedule_recalc();
+                    }
                     force_redraw = true;
                 }
-                SIGWINCH => layout.schedule_recalc(),
+                SIGWINCH => {
+                    for timer in timers.iter_mut() {
+                        timer.layout.schedule_recalc();
+                    }
+                }
                 // Exit main loop on SIGTERM and SIGINT.
                 SIGTERM | SIGINT => break 'main,
-                // Reset clock on SIGUSR1.
+                // Reset focused clock on SIGUSR1.
                 SIGUSR1 => {
-                    clock.reset();
-                    alarm_roster.reset_all();
-                    layout.schedule_recalc();
+                    timers[focus].reset();
                     force_redraw = true;
                 }
-                // (Un-)Pause clock on SIGUSR2.
+                // (Un-)Pause focused clock on SIGUSR2.
                 SIGUSR2 => {
-                    clock.toggle();
+                    timers[focus].toggle();
                     force_redraw = true;
                 }
                 // We didn't register anything else.
@@ -344,17 +652,58 @@ pub fn run(
 
     }
 
+    write_events(&mut config, &mut timers);
+
     // Main loop exited. Clear screen and restore cursor.
     write!(stdout, "{}{}{}", clear::All, cursor::Restore, cursor::Show)?;
     stdout.flush()?;
+    drop(title);
+
+    if let Some(path) = &config.socket {
+        control::remove(path);
+    }
 
+    // Leave raw mode before printing statistics of sequences.
+    drop(stdout);
+    export_laps(&config, &timers);
+    for timer in timers.iter() {
+        if let Some(summary) = timer.sequence.as_ref().and_then(|s| s.summary()) {
+            println!("{}: {}", timer.name, summary);
+        }
+    }
     Ok(())
 }
 
 pub struct Config {
     quit: bool,
     font: &'static font::Font,
+    // Fonts to cycle through.
+    fonts: font::Fonts,
     commands: Cradle,
+    // Timers to run. Alarms of the first one are passed to "run" separately.
+    timers: Vec<Setup>,
+    resume: bool,
+    socket: Option<PathBuf>,
+    status: Option<status::Format>,
+    // Sink of the event stream.
+    events: Option<std::fs::File>,
+    tenths: bool,
+    // Scale the clock to fill the window.
+    scale: bool,
+    // Show the clock in the window title.
+    title: bool,
+    notify: notify::Backend,
+    // File to export laps to.
+    laps: Option<PathBuf>,
+    // Interval of repeated notifications about alarms not acknowledged.
+    // Zero, the default, disables nagging.
+    nag: u32,
+    snooze: u32,
+    // Set in the config file.
+    palette: palette::Palette,
+    alarms: AlarmRoster,
+    keys: HashMap<Key, Key>,
+    recipes: Recipes,
 }
 
 impl Config {
@@ -366,8 +715,29 @@ impl Config {
         let mut config = Config {
             quit: false,
             font: &font::NORMAL,
+            fonts: font::Fonts::scan(),
             commands: Cradle::new(),
+            timers: vec![Setup::new(None)],
+            resume: false,
+            socket: control::default_path(),
+            status: None,
+            events: None,
+            tenths: false,
+            scale: false,
+            title: false,
+            notify: notify::Backend::Auto,
+            laps: None,
+            nag: 0,
+            snooze: 5 * 60,
+            palette: palette::Palette::new(),
+            alarms: AlarmRoster::new(),
+            keys: HashMap::new(),
+            recipes: Recipes::load()?,
         };
+        settings::load(&mut config)?;
+        // Commands given on the command line replace those of the config
+        // file.
+        let mut exec_given = false;
         let mut iter = args.skip(1);
 
         while let Some(arg) = iter.next() {
@@ -375,6 +745,7 @@ impl Config {
                 "-h" | "--help" => {
                     // Print usage information and exit
                     println!("{}", USAGE);
+                    println!("\nFONTS:   {}", font::Fonts::scan().names().join(", "));
                     process::exit(0);
                 }
                 "-v" | "--version" => {
@@ -383,8 +754,195 @@ impl Config {
                 }
                 "-p" | "--plain" => config.font = &font::PLAIN,
                 "-q" | "--quit" => config.quit = true,
+                "-r" | "--resume" => config.resume = true,
+                "--tenths" => config.tenths = true,
+                "--scale" => config.scale = true,
+                "--font" => {
+                    let name = match iter.next() {
+                        Some(name) => name,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    config.font = config.fonts.find(&name)?;
+                }
+                "--font-file" => {
+                    let path = match iter.next() {
+                        Some(path) => path,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    config.font = font::load(&path)?;
+                }
+                "--figlet-font" => {
+                    let path = match iter.next() {
+                        Some(path) => path,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    config.font = clock::figlet::load(&path)?;
+                }
+                "--title" => config.title = true,
+                // Switch off options set in the config file.
+                "--no-quit" => config.quit = false,
+                "--no-tenths" => config.tenths = false,
+                "--no-scale" => config.scale = false,
+                "--no-title" => config.title = false,
+                "--status" => {
+                    let format = match iter.next() {
+                        Some(format) => format,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    match status::Format::parse(&format) {
+                        Some(format) => config.status = Some(format),
+                        None => return Err(format!("Unknown status format \"{}\".", format)),
+                    }
+                }
+                "--notify" => {
+                    let name = match iter.next() {
+                        Some(name) => name,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    match notify::Backend::parse(&name) {
+                        Some(backend) => config.notify = backend,
+                        None => return Err(format!("Unknown notification backend \"{}\".", name)),
+                    }
+                }
+                "--events" => {
+                    let path = match iter.next() {
+                        Some(path) => path,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    config.events = Some(events::open(&path)?);
+                }
+                "--events-fd" => {
+                    let fd = match iter.next() {
+                        Some(fd) => fd,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    config.events = Some(events::open_fd(&fd)?);
+                }
+                "--laps" => {
+                    if let Some(path) = iter.next() {
+                        config.laps = Some(PathBuf::from(path));
+                    } else {
+                        return Err(format!("Missing parameter to \"{}\".", arg));
+                    }
+                }
+                "--socket" => {
+                    if let Some(path) = iter.next() {
+                        config.socket = Some(PathBuf::from(path));
+                    } else {
+                        return Err(format!("Missing parameter to \"{}\".", arg));
+                    }
+                }
+                "-t" | "--timer" => {
+                    let name = match iter.next() {
+                        Some(name) if !name.is_empty() => name,
+                        _ => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    if config.timers.len() == 1
+                        && config.timers[0].name.is_none()
+                        && config.timers[0].sequence.is_none()
+                        && alarm_roster.is_empty()
+                    {
+                        // Name the first timer if it has no alarms yet.
+                        config.timers[0].name = Some(name);
+                    } else {
+                        // Additional timers wait to be started.
+                        let mut setup = Setup::new(Some(name));
+                        setup.paused = true;
+                        config.timers.push(setup);
+                    }
+                }
+                "-c" | "--countdown" => {
+                    if let Some(time) = iter.next() {
+                        // Add target as alarm, so it is raised at zero.
+                        let alarm_roster = match config.timers[1..].last_mut() {
+                            Some(setup) => &mut setup.alarm_roster,
+                            None => &mut *alarm_roster,
+                        };
+                        match alarm_roster.add(&time) {
+                            Ok(time) => {
+                                let setup = config.timers.last_mut().unwrap();
+                                setup.target = Some(time);
+                                setup.count_down = true;
+                            }
+                            Err(error) => return Err(format!("Error adding \"{}\" as countdown target. ({})", time, error)),
+                        }
+                    } else {
+                        return Err(format!("Missing parameter to \"{}\".", arg));
+                    }
+                }
+                "-n" | "--nag" | "-s" | "--snooze" => {
+                    let time = match iter.next() {
+                        Some(time) => alarm::parse_time(&time).map_err(|error| {
+                            format!("Error parsing \"{}\" as time. ({})", time, error)
+                        })?,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    if time == 0 {
+                        return Err(format!("Parameter to \"{}\" evaluates to zero.", arg));
+                    }
+                    if arg == "-n" || arg == "--nag" {
+                        config.nag = time;
+                    } else {
+                        config.snooze = time;
+                    }
+                }
+                "--recipe" => {
+                    let recipe = match iter.next() {
+                        Some(recipe) => recipe,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    if recipe.contains('=') {
+                        // Define recipe for use with @NAME.
+                        if let Err(error) = config.recipes.define(&recipe) {
+                            return Err(format!("Error defining recipe \"{}\". ({})", recipe, error));
+                        }
+                    } else {
+                        // Add alarms of the recipe to the timer given last.
+                        let alarm_roster = match config.timers[1..].last_mut() {
+                            Some(setup) => &mut setup.alarm_roster,
+                            None => &mut *alarm_roster,
+                        };
+                        let added = config
+                            .recipes
+                            .get(&recipe)
+                            .and_then(|alarms| alarm_roster.add_recipe(alarms));
+                        if let Err(error) = added {
+                            return Err(format!("Error adding recipe \"{}\". ({})", recipe, error));
+                        }
+                    }
+                }
+                "--pomodoro" => {
+                    let spec = match iter.next() {
+                        Some(spec) => spec,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    let sequence = Sequence::pomodoro(&spec)
+                        .map_err(|error| format!("Error parsing \"{}\". ({})", spec, error))?;
+                    config.timers.last_mut().unwrap().sequence = Some(sequence);
+                }
+                "--steps" | "--auto-steps" => {
+                    let steps = match iter.next() {
+                        Some(steps) => steps,
+                        None => return Err(format!("Missing parameter to \"{}\".", arg)),
+                    };
+                    let auto = arg == "--auto-steps";
+                    // Steps may be taken from a recipe.
+                    let sequence = match steps.strip_prefix('@') {
+                        Some(name) => config
+                            .recipes
+                            .get(name)
+                            .and_then(|alarms| Sequence::new(alarms, auto)),
+                        None => Sequence::parse(&steps, auto),
+                    }
+                    .map_err(|error| format!("Error parsing steps \"{}\". ({})", steps, error))?;
+                    config.timers.last_mut().unwrap().sequence = Some(sequence);
+                }
                 "-e" | "--exec" => {
                     if let Some(cmd) = iter.next() {
+                        if !exec_given {
+                            config.commands = Cradle::new();
+                            exec_given = true;
+                        }
                         config.commands.add(Cradle::parse(cmd));
                     } else {
                         return Err(format!("Missing parameter to \"{}\".", arg));
@@ -395,17 +953,166 @@ impl Config {
                     return Err(format!("Unrecognized option: \"{}\"\nUse \"-h\" or \"--help\" for a list of valid command line options.", any));
                 }
                 any => {
-                    // Alarm to add.
-                    if let Err(error) = alarm_roster.add(&String::from(any)) {
+                    // Alarm to add to the timer given last.
+                    let alarm_roster = match config.timers[1..].last_mut() {
+                        Some(setup) => &mut setup.alarm_roster,
+                        None => &mut *alarm_roster,
+                    };
+                    if let Err(error) = alarm_roster.add(any) {
                         return Err(format!("Error adding \"{}\" as alarm. ({})", any, error));
                     }
                 }
             }
         }
+
+        // Fall back to the alarms of the config file.
+        if !config.resume
+            && config.timers.len() == 1
+            && config.timers[0].sequence.is_none()
+            && alarm_roster.is_empty()
+        {
+            *alarm_roster = std::mem::take(&mut config.alarms);
+        }
+
+        if config.resume {
+            if config.timers.len() > 1
+                || config.timers[0].name.is_some()
+                || config.timers[0].target.is_some()
+                || config.timers[0].sequence.is_some()
+                || !alarm_roster.is_empty()
+            {
+                return Err(String::from("Alarms and timers can not be given when resuming a session."));
+            }
+            config.timers = session::load()?;
+            // Alarms of the first timer are kept in "alarm_roster".
+            *alarm_roster = std::mem::take(&mut config.timers[0].alarm_roster);
+        }
+        config.notify = config.notify.resolve();
+        // Fall back to the normal font if the terminal lacks glyphs.
+        if !font::supported(config.font, |var| env::var(var).ok()) {
+            config.font = &font::NORMAL;
+        }
+        config.fonts.keep(config.font);
         Ok(config)
     }
 }
 
+// Time to wait for input. Wakes up right when one of the clocks changes or an
+// alarm set to a time of day is due, but at least four times a second to
+// process signals.
+fn timeout(timers: &[Timer]) -> time::Duration {
+    timers
+        .iter()
+        .filter_map(Timer::until_next)
+        .fold(time::Duration::from_millis(250), time::Duration::min)
+}
+
+// Export laps on exit.
+fn export_laps(config: &Config, timers: &[Timer]) {
+    if let Some(path) = &config.laps {
+        if timers.iter().any(|timer| !timer.alarm_roster.laps.is_empty()) {
+            if let Err(error) = lap::export(path, timers) {
+                eprintln!("Error exporting laps to {}. ({})", path.display(), error);
+            }
+        }
+    }
+}
+
+// Write events queued by the timers and by spawned commands.
+fn write_events(config: &mut Config, timers: &mut [Timer]) {
+    for timer in timers.iter_mut() {
+        timer.alarm_roster.events.flush(&mut config.events, Some(&timer.name));
+    }
+    config.commands.events.flush(&mut config.events, None);
+}
+
+// Send a command to the control socket of a running instance. Returns the
+// reply on success.
+pub fn ctl(args: impl Iterator<Item = String>) -> Result<String, String> {
+    control::send(args)
+}
+
+// Process command received on the control socket. Returns the reply.
+fn handle_command(
+    timer: &mut Timer,
+    command: &str,
+    recipes: &Recipes,
+) -> Result<String, &'static str> {
+    let (command, arg) = match command.split_once(' ') {
+        Some((command, arg)) => (command, arg.trim()),
+        None => (command, ""),
+    };
+    let Timer {
+        clock,
+        alarm_roster,
+        countdown,
+        layout,
+        ..
+    } = timer;
+
+    match command {
+        "add" => {
+            recipes.add(alarm_roster, arg)?;
+            layout.set_roster_width(alarm_roster.width());
+            countdown.reset();
+        }
+        "remove" => {
+            alarm_roster.remove(arg).ok_or("No such alarm.")?;
+            layout.set_roster_width(alarm_roster.width());
+            countdown.reset();
+        }
+        "lap" => return Ok(timer.lap().to_string()),
+        "acknowledge" => {
+            alarm_roster.acknowledge();
+            clock.flash = false;
+        }
+        "pause" if !clock.paused => timer.toggle(),
+        "resume" if clock.paused => timer.toggle(),
+        "pause" | "resume" => (),
+        "reset" => timer.reset(),
+        "shift" => {
+            let (backwards, time) = match arg.strip_prefix('-') {
+                Some(time) => (true, time),
+                None => (false, arg.strip_prefix('+').unwrap_or(arg)),
+            };
+            let secs = alarm::parse_time(time)?;
+            let secs = i32::try_from(secs).map_err(|_| "Value too large.")?;
+            timer.shift(if backwards { -secs } else { secs });
+        }
+        // State, elapsed seconds and, if any, seconds until the next alarm
+        // and its label.
+        "status" => {
+            let mut status = format!(
+                "{} {}",
+                if clock.paused { "paused" } else { "running" },
+                clock.total()
+            );
+            if let Some(alarm) = alarm_roster.next() {
+                let remaining = alarm.time.saturating_sub(clock.total());
+                status.push_str(&format!(" {} {}", remaining, alarm.label));
+            }
+            return Ok(status);
+        }
+        _ => return Err("Unknown command."),
+    }
+    Ok(String::new())
+}
+
+// Announce alarm.
+fn alert<W: Write>(
+    stdout: &mut RawTerminal<W>,
+    commands: &mut Cradle,
+    backend: notify::Backend,
+    alarm: &Alarm,
+) -> Result<(), std::io::Error> {
+    // Ask the terminal for a notification, or just ring the bell.
+    notify::send(stdout, backend, alarm.nominal_time(), &alarm.label)?;
+
+    // Run commands.
+    commands.run_all(alarm.nominal_time(), &alarm.label);
+    Ok(())
+}
+
 // Prepare to suspend execution. Called on SIGTSTP.
 fn suspend<W: Write>(stdout: &mut RawTerminal<W>) -> Result<(), std::io::Error> {
     write!(
@@ -423,7 +1130,7 @@ fn suspend<W: Write>(stdout: &mut RawTerminal<W>) -> Result<(), std::io::Error>
         );
     });
 
-    if let Err(error) = low_level::emulate_default_handler(SIGSTOP as i32) {
+    if let Err(error) = low_level::emulate_default_handler(SIGSTOP) {
         eprintln!("Error raising SIGTSTP: {}", error);
     }
 
@@ -15,10 +15,26 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
-use kitchentimer::{run, AlarmRoster, Config};
+use kitchentimer::{ctl, run, AlarmRoster, Config};
 use std::{env, process};
 
 fn main() {
+    // Send command to a running instance.
+    if env::args().nth(1).as_deref() == Some("ctl") {
+        match ctl(env::args().skip(2)) {
+            Ok(reply) => {
+                if !reply.is_empty() {
+                    println!("{}", reply);
+                }
+                process::exit(0);
+            }
+            Err(e) => {
+                eprintln!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
     let args = env::args();
     let mut alarm_roster = AlarmRoster::new();
     // Parse command line arguments into config and alarm roster.
@@ -34,9 +50,6 @@ fn main() {
             eprintln!("Error while reading alarm times from stdin. ({})", e);
             process::exit(1);
         }
-    } else {
-        // We don't need stdin anymore.
-        drop(stdin);
     }
 
     // Run main loop. Returns spawned child process if any.
@@ -0,0 +1,120 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Desktop notifications sent by the terminal emulator itself, requested via
+// operating system command (OSC) escape sequences.
+
+use crate::consts::ui::NAME;
+use crate::utils::format_duration;
+use std::env;
+use std::io::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Backend {
+    // Pick one based on the environment.
+    Auto,
+    // ASCII bell code only.
+    Bell,
+    // OSC 9, understood by iTerm2, Windows Terminal, ConEmu and others.
+    Osc9,
+    // OSC 777 "notify", understood by urxvt, foot, kitty and VTE based
+    // terminals.
+    Osc777,
+    // OSC 99, kitty's own notification protocol.
+    Osc99,
+}
+
+impl Backend {
+    pub fn parse(name: &str) -> Option<Backend> {
+        match name {
+            "auto" => Some(Backend::Auto),
+            "bell" => Some(Backend::Bell),
+            "osc9" => Some(Backend::Osc9),
+            "osc777" => Some(Backend::Osc777),
+            "osc99" => Some(Backend::Osc99),
+            _ => None,
+        }
+    }
+
+    // Replace "Auto" by a backend the terminal is known to support, falling
+    // back to the bell.
+    pub fn resolve(self) -> Backend {
+        match self {
+            Backend::Auto => detect(|var| env::var(var).ok()),
+            backend => backend,
+        }
+    }
+}
+
+// Guess the terminal from environment variables it sets. Terminal
+// multiplexers swallow escape sequences they do not know, so stick to the
+// bell inside of them.
+pub fn detect<F: Fn(&str) -> Option<String>>(var: F) -> Backend {
+    if var("TMUX").is_some() || var("STY").is_some() {
+        return Backend::Bell;
+    }
+    if var("KITTY_WINDOW_ID").is_some() {
+        return Backend::Osc99;
+    }
+    if var("WT_SESSION").is_some() || var("ConEmuPID").is_some() {
+        return Backend::Osc9;
+    }
+    match var("TERM_PROGRAM").as_deref() {
+        Some("iTerm.app") | Some("WezTerm") | Some("ghostty") => return Backend::Osc9,
+        _ => (),
+    }
+    if var("VTE_VERSION").is_some() {
+        return Backend::Osc777;
+    }
+    match var("TERM") {
+        Some(term) if term.starts_with("rxvt") || term.starts_with("foot") => Backend::Osc777,
+        _ => Backend::Bell,
+    }
+}
+
+// Escape sequence announcing an alarm with "label" going off at "time".
+// Always includes the bell, which terminals also use to mark the window as
+// urgent.
+pub fn sequence(backend: Backend, time: u32, label: &str) -> String {
+    // Control characters would end the sequence prematurely.
+    let label: String = label.chars().filter(|c| !c.is_control()).collect();
+    let body = if label.is_empty() {
+        format!("Alarm at {}", format_duration(time))
+    } else {
+        format!("{} ({})", label, format_duration(time))
+    };
+    let osc = match backend {
+        Backend::Auto | Backend::Bell => String::new(),
+        Backend::Osc9 => format!("\x1b]9;{}: {}\x07", NAME, body),
+        Backend::Osc777 => format!("\x1b]777;notify;{};{}\x07", NAME, body),
+        // Title and body are sent separately. "d=0" announces more to come.
+        Backend::Osc99 => format!(
+            "\x1b]99;i={0}:d=0;{0}\x1b\\\x1b]99;i={0}:d=1:p=body;{1}\x1b\\",
+            NAME, body
+        ),
+    };
+    format!("{}\x07", osc)
+}
+
+pub fn send<W: Write>(
+    stdout: &mut W,
+    backend: Backend,
+    time: u32,
+    label: &str,
+) -> Result<(), std::io::Error> {
+    write!(stdout, "{}", sequence(backend, time, label))
+}
@@ -0,0 +1,74 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Colors of alarms, timers and the clock. The defaults may be replaced by
+// the config file.
+
+use crate::consts::COLOR;
+use termion::color::{self, Color};
+
+pub type Paint = Box<dyn Color>;
+
+// Colors in use. Empty unless replaced by the config file.
+#[derive(Default)]
+pub struct Palette {
+    colors: Vec<Paint>,
+}
+
+impl Palette {
+    pub fn new() -> Palette {
+        Palette { colors: Vec::new() }
+    }
+
+    // Add color, replacing the defaults.
+    pub fn push(&mut self, paint: Paint) {
+        self.colors.push(paint);
+    }
+
+    pub fn get(&self, index: usize) -> &dyn Color {
+        match self.colors.len() {
+            0 => COLOR[index % COLOR.len()],
+            len => &*self.colors[index % len],
+        }
+    }
+}
+
+// Parse color given by name, as ANSI value (0-255) or as #RRGGBB.
+pub fn parse(input: &str) -> Result<Paint, &'static str> {
+    const NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    if let Some(hex) = input.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err("Expected color in #RRGGBB format.");
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        return Ok(Box::new(color::Rgb(channel(0), channel(2), channel(4))));
+    }
+    if let Ok(value) = input.parse::<u8>() {
+        return Ok(Box::new(color::AnsiValue(value)));
+    }
+    let (name, offset) = match input.strip_prefix("light-") {
+        Some(name) => (name, 8),
+        None => (input, 0),
+    };
+    match NAMES.iter().position(|n| *n == name) {
+        Some(i) => Ok(Box::new(color::AnsiValue(i as u8 + offset))),
+        None => Err("Unknown color."),
+    }
+}
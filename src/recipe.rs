@@ -0,0 +1,150 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Recipes are named lists of alarms, read from the recipes file next to the
+// config file. A line holds the name of a recipe, the indented lines
+// following it its alarms:
+//
+//   pasta
+//       8:00/al dente
+//       10:00/soft
+
+use crate::alarm::{self, AlarmRoster};
+use crate::settings;
+use std::collections::HashMap;
+use std::{fs, io};
+
+pub struct Recipes {
+    // Alarms of each recipe or why they cannot be used.
+    map: HashMap<String, Result<Vec<String>, &'static str>>,
+}
+
+impl Recipes {
+    pub fn new() -> Recipes {
+        Recipes {
+            map: HashMap::new(),
+        }
+    }
+
+    // Read recipes file. A missing file is not an error.
+    pub fn load() -> Result<Recipes, String> {
+        let path = match settings::dir() {
+            Some(dir) => dir.join("recipes"),
+            None => return Ok(Recipes::new()),
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Recipes::new()),
+            Err(e) => {
+                return Err(format!("Error reading recipes file {}. ({})", path.display(), e))
+            }
+        };
+        Recipes::parse(&content).map_err(|(n, msg)| {
+            format!("Error in recipes file {}:{}. ({})", path.display(), n, msg)
+        })
+    }
+
+    // Parse recipes. Returns the line number and a message on error. Recipes
+    // holding invalid alarms only fail when used.
+    pub fn parse(content: &str) -> Result<Recipes, (usize, String)> {
+        let mut recipes = Recipes::new();
+        let mut current: Option<&str> = None;
+
+        for (n, line) in content.lines().enumerate() {
+            let error = |msg: &str| (n + 1, msg.to_string());
+            if line.trim().is_empty() || line.trim_start().starts_with('#') {
+                continue;
+            }
+            if !line.starts_with(char::is_whitespace) {
+                let name = line.trim();
+                if !is_name(name) {
+                    return Err(error("Invalid recipe name."));
+                }
+                if recipes.map.insert(name.to_string(), Ok(Vec::new())).is_some() {
+                    return Err(error("Duplicate recipe name."));
+                }
+                current = Some(name);
+                continue;
+            }
+            let name = current.ok_or_else(|| error("Alarm outside of recipe."))?;
+            let recipe = recipes.map.get_mut(name).unwrap();
+            if let Ok(alarms) = recipe {
+                let input = line.trim();
+                match alarm::validate(input) {
+                    Ok(()) => alarms.push(input.to_string()),
+                    Err(msg) => *recipe = Err(msg),
+                }
+            }
+        }
+        Ok(recipes)
+    }
+
+    // Define recipe given as NAME=ALARM[;ALARM...].
+    pub fn define(&mut self, input: &str) -> Result<(), &'static str> {
+        let (name, list) = input.split_once('=').ok_or("Expected NAME=ALARMS.")?;
+        let name = name.trim();
+        if !is_name(name) {
+            return Err("Invalid recipe name.");
+        }
+        let alarms: Vec<String> = list
+            .split(';')
+            .map(str::trim)
+            .filter(|a| !a.is_empty())
+            .map(String::from)
+            .collect();
+        if alarms.is_empty() {
+            return Err("Recipe has no alarms.");
+        }
+        for input in &alarms {
+            alarm::validate(input)?;
+        }
+        // Replaces a recipe of the same name read from the recipes file.
+        self.map.insert(name.to_string(), Ok(alarms));
+        Ok(())
+    }
+
+    // Alarms of the recipe called "name".
+    pub fn get(&self, name: &str) -> Result<&[String], &'static str> {
+        match self.map.get(name) {
+            Some(Ok(alarms)) => Ok(alarms),
+            Some(Err(msg)) => Err(msg),
+            None => Err("No such recipe."),
+        }
+    }
+
+    // Add alarms given as ALARM[/LABEL] or @RECIPE to "alarm_roster".
+    pub fn add(&self, alarm_roster: &mut AlarmRoster, input: &str) -> Result<(), &'static str> {
+        match reference(input) {
+            Some(name) => {
+                let alarms = self.get(name)?;
+                alarm_roster.add_recipe(alarms)
+            }
+            None => alarm_roster.add(input).map(|_| ()),
+        }
+    }
+}
+
+// Recipe names start with a letter, telling them apart from times of day.
+fn is_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic())
+        && !name.contains(|c: char| c.is_whitespace() || c == '/')
+}
+
+// Name of the recipe referred to as @NAME.
+fn reference(input: &str) -> Option<&str> {
+    input.trim().strip_prefix('@').filter(|name| is_name(name))
+}
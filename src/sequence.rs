@@ -0,0 +1,318 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Steps run one after another. Every step adds an alarm going off once its
+// time has passed. The next step begins when that alarm is acknowledged, or
+// right away if "auto" is set. Pomodoro mode is a sequence of work and break
+// periods starting over once done.
+
+use crate::alarm::{parse_time, AlarmRoster};
+use crate::clock::Clock;
+use crate::consts::LABEL_SIZE_LIMIT;
+use crate::utils::{format_duration, grapheme_truncate};
+use std::io::{self, Write};
+use termion::color::AnsiValue;
+
+// Colors of pomodoro phases: work, short and long break. Fixed, so phases
+// stay apart whatever colors the config file sets.
+const PHASE_COLORS: [AnsiValue; 3] = [AnsiValue(9), AnsiValue(10), AnsiValue(12)];
+
+pub struct Step {
+    pub time: u32,
+    pub label: String,
+    // Clock color while the step is running.
+    pub color: Option<AnsiValue>,
+}
+
+pub struct Sequence {
+    steps: Vec<Step>,
+    // Index of the current step.
+    current: usize,
+    // Set once the current step began.
+    begun: bool,
+    pub auto: bool,
+    // Start over after the last step.
+    repeat: bool,
+    // Number of times all steps were completed.
+    rounds: u32,
+    // Label, count and total time of completed steps.
+    completed: Vec<(String, u32, u32)>,
+}
+
+impl Sequence {
+    // Parse steps given as TIME[/LABEL] each.
+    pub fn new<S: AsRef<str>>(inputs: &[S], auto: bool) -> Result<Sequence, &'static str> {
+        let mut steps = Vec::new();
+        for input in inputs {
+            let (time, label) = match input.as_ref().split_once('/') {
+                Some((time, label)) => (time, label.trim().to_string()),
+                None => (input.as_ref(), String::new()),
+            };
+            let time = parse_time(time.trim())?;
+            if time == 0 {
+                return Err("Evaluates to zero.");
+            }
+            let mut label = match label {
+                label if label.is_empty() => format!("Step {}", steps.len() + 1),
+                label => label,
+            };
+            grapheme_truncate(&mut label, LABEL_SIZE_LIMIT, '…');
+            steps.push(Step {
+                time,
+                label,
+                color: None,
+            });
+        }
+        if steps.is_empty() {
+            return Err("No steps given.");
+        }
+        Ok(Sequence {
+            steps,
+            current: 0,
+            begun: false,
+            auto,
+            repeat: false,
+            rounds: 0,
+            completed: Vec::new(),
+        })
+    }
+
+    // Pomodoro cycle given as WORK/SHORT/LONG[/COUNT]. COUNT work periods
+    // separated by short breaks are followed by a long break.
+    pub fn pomodoro(input: &str) -> Result<Sequence, &'static str> {
+        let times: Vec<&str> = input.split('/').map(str::trim).collect();
+        if times.len() < 3 || times.len() > 4 {
+            return Err("Expected WORK/SHORT/LONG[/COUNT].");
+        }
+        let mut phases = [0; 3];
+        for (phase, time) in phases.iter_mut().zip(times.iter()) {
+            *phase = parse_time(time)?;
+            if *phase == 0 {
+                return Err("Evaluates to zero.");
+            }
+        }
+        let count = match times.get(3) {
+            Some(count) => count.parse::<usize>().map_err(|_| "Invalid count.")?,
+            None => 4,
+        };
+        if count == 0 || count > 100 {
+            return Err("Count out of range.");
+        }
+
+        let mut steps = Vec::new();
+        for i in 1..=count {
+            let phase = if i < count { 1 } else { 2 };
+            steps.push(Step {
+                time: phases[0],
+                label: String::from("Work"),
+                color: Some(PHASE_COLORS[0]),
+            });
+            steps.push(Step {
+                time: phases[phase],
+                label: String::from(if i < count { "Short break" } else { "Long break" }),
+                color: Some(PHASE_COLORS[phase]),
+            });
+        }
+        Ok(Sequence {
+            steps,
+            current: 0,
+            begun: false,
+            auto: true,
+            repeat: true,
+            rounds: 0,
+            completed: Vec::new(),
+        })
+    }
+
+    // Parse steps given as TIME[/LABEL];TIME[/LABEL]...
+    pub fn parse(input: &str, auto: bool) -> Result<Sequence, &'static str> {
+        let inputs: Vec<&str> = input
+            .split(';')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect();
+        Sequence::new(&inputs, auto)
+    }
+
+    // Current step and its index. None once all steps are done.
+    pub fn current(&self) -> Option<(usize, &Step)> {
+        self.steps.get(self.current).map(|step| (self.current, step))
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    // Round currently running, if starting over.
+    pub fn round(&self) -> Option<u32> {
+        if self.repeat {
+            Some(self.rounds + 1)
+        } else {
+            None
+        }
+    }
+
+    // Begin the first step or the next one, if the current step is done.
+    // Returns true on changes.
+    pub fn advance(&mut self, alarm_roster: &mut AlarmRoster, clock: &Clock) -> bool {
+        if self.current >= self.steps.len() {
+            return false;
+        }
+        if self.begun {
+            match alarm_roster.step_state() {
+                // Step still running or waiting to be acknowledged.
+                Some((exceeded, pending)) if !exceeded || pending && !self.auto => {
+                    return false
+                }
+                // Step done or its alarm removed. Only steps whose alarm
+                // went off count as completed.
+                state => {
+                    if let Some((true, _)) = state {
+                        self.record();
+                    }
+                    self.current += 1;
+                    self.begun = false;
+                    if self.repeat && self.current == self.steps.len() {
+                        // Start over, dropping alarms of the last round.
+                        alarm_roster.remove_steps();
+                        self.current = 0;
+                        self.rounds += 1;
+                    }
+                }
+            }
+        }
+        if let Some(step) = self.steps.get(self.current) {
+            alarm_roster.add_step(clock.total().saturating_add(step.time), step.time, &step.label);
+            self.begun = true;
+        }
+        true
+    }
+
+    // Count current step as completed.
+    fn record(&mut self) {
+        let step = &self.steps[self.current];
+        match self.completed.iter_mut().find(|(label, _, _)| *label == step.label) {
+            Some((_, count, total)) => {
+                *count += 1;
+                *total = total.saturating_add(step.time);
+            }
+            None => self.completed.push((step.label.clone(), 1, step.time)),
+        }
+    }
+
+    // Summary of completed steps. None if nothing was completed yet.
+    pub fn summary(&self) -> Option<String> {
+        if self.completed.is_empty() {
+            return None;
+        }
+        let mut parts: Vec<String> = self
+            .completed
+            .iter()
+            .map(|(label, count, total)| format!("{} {}x ({})", label, count, format_duration(*total)))
+            .collect();
+        if self.repeat {
+            parts.push(format!("{} completed rounds", self.rounds));
+        }
+        Some(parts.join(", "))
+    }
+
+    // Start over with the first step. Removes alarms of previous steps.
+    pub fn restart(&mut self, alarm_roster: &mut AlarmRoster) {
+        alarm_roster.remove_steps();
+        self.current = 0;
+        self.begun = false;
+    }
+
+    // Write state to the session file. Steps and completed steps follow the
+    // sequence itself.
+    pub fn save<W: Write>(&self, out: &mut W) -> Result<(), io::Error> {
+        let mut flags = String::new();
+        if self.auto {
+            flags.push('a');
+        }
+        if self.repeat {
+            flags.push('r');
+        }
+        if self.begun {
+            flags.push('b');
+        }
+        if flags.is_empty() {
+            flags.push('-');
+        }
+        writeln!(out, "sequence {} {} {}", flags, self.current, self.rounds)?;
+        for step in &self.steps {
+            let color = step.color.map_or(String::from("-"), |c| c.0.to_string());
+            writeln!(out, "step {} {} {}", step.time, color, step.label)?;
+        }
+        for (label, count, total) in &self.completed {
+            writeln!(out, "completed {} {} {}", count, total, label)?;
+        }
+        Ok(())
+    }
+
+    // Restore sequence from its state in the session file. Steps are added
+    // by "restore_step".
+    pub fn restore(state: &str) -> Result<Sequence, &'static str> {
+        let fields: Vec<&str> = state.split(' ').collect();
+        let (flags, current, rounds) = match fields[..] {
+            [flags, current, rounds] => (flags, current, rounds),
+            _ => return Err("Malformed sequence."),
+        };
+        Ok(Sequence {
+            steps: Vec::new(),
+            current: current.parse().map_err(|_| "Malformed sequence.")?,
+            begun: flags.contains('b'),
+            auto: flags.contains('a'),
+            repeat: flags.contains('r'),
+            rounds: rounds.parse().map_err(|_| "Malformed sequence.")?,
+            completed: Vec::new(),
+        })
+    }
+
+    // Restore step from its state in the session file.
+    pub fn restore_step(&mut self, state: &str) -> Result<(), &'static str> {
+        let mut fields = state.splitn(3, ' ');
+        let (time, color, label) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(time), Some(color), Some(label)) => (time, color, label),
+            _ => return Err("Malformed step."),
+        };
+        let color = match color {
+            "-" => None,
+            color => Some(AnsiValue(color.parse().map_err(|_| "Malformed step.")?)),
+        };
+        self.steps.push(Step {
+            time: time.parse().map_err(|_| "Malformed step.")?,
+            label: label.to_string(),
+            color,
+        });
+        Ok(())
+    }
+
+    // Restore completed steps from their state in the session file.
+    pub fn restore_completed(&mut self, state: &str) -> Result<(), &'static str> {
+        let mut fields = state.splitn(3, ' ');
+        match (fields.next(), fields.next(), fields.next()) {
+            (Some(count), Some(total), Some(label)) => {
+                let count = count.parse().map_err(|_| "Malformed completed steps.")?;
+                let total = total.parse().map_err(|_| "Malformed completed steps.")?;
+                self.completed.push((label.to_string(), count, total));
+                Ok(())
+            }
+            _ => Err("Malformed completed steps."),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// The session file keeps the state of all timers, so a session can be resumed
+// after the terminal died. One entry per line:
+//
+//   timer NAME
+//   start UNIX_TIME     Wall time at which a running clock read zero.
+//   paused DAYS SECS    Clock state of a paused clock.
+//   target SECS         Countdown target.
+//   countdown           Clock is counting down.
+//   alarm FLAGS TIME SPEC/LABEL
+//   lap MILLIS
+//   sequence FLAGS CURRENT ROUNDS
+//   step SECS COLOR LABEL           Steps of the sequence, COLOR as ANSI value.
+//   completed COUNT SECS LABEL      Steps of the sequence completed so far.
+//
+// Entries following "timer" belong to that timer.
+
+use crate::sequence::Sequence;
+use crate::timer::{Setup, Timer};
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+const HEADER: &str = "# kitchentimer session";
+
+// Location of the session file. Follows the XDG base directory
+// specification.
+fn path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".local/state"),
+    };
+    Some(dir.join(env!("CARGO_PKG_NAME")).join("session"))
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+// Write state of all timers to the session file.
+pub fn save(timers: &[Timer]) -> Result<(), io::Error> {
+    let path = path()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No state directory."))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Replace the file in one go, so we never leave a truncated one behind.
+    let tmp = path.with_extension("tmp");
+    let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+    write(&mut file, timers, unix_time())?;
+    file.flush()?;
+    drop(file);
+    fs::rename(&tmp, &path)
+}
+
+// Write state of all timers at UNIX time "now".
+pub fn write<W: Write>(out: &mut W, timers: &[Timer], now: u64) -> Result<(), io::Error> {
+    writeln!(out, "{}", HEADER)?;
+    for timer in timers {
+        let clock = &timer.clock;
+        writeln!(out, "timer {}", timer.name)?;
+        if clock.paused {
+            writeln!(out, "paused {} {}", clock.days, clock.elapsed.as_secs())?;
+        } else {
+            writeln!(out, "start {}", now.saturating_sub(clock.total() as u64))?;
+        }
+        if let Some(target) = timer.target {
+            writeln!(out, "target {}", target)?;
+        }
+        if clock.count_down {
+            writeln!(out, "countdown")?;
+        }
+        timer.alarm_roster.save(out)?;
+        timer.alarm_roster.laps.save(out)?;
+        if let Some(sequence) = &timer.sequence {
+            sequence.save(out)?;
+        }
+    }
+    Ok(())
+}
+
+// Read timers from the session file.
+pub fn load() -> Result<Vec<Setup>, String> {
+    let path = path().ok_or("Unable to locate session file.")?;
+    let content = fs::read_to_string(&path)
+        .map_err(|e| format!("Error reading session file {}. ({})", path.display(), e))?;
+    parse(&content, unix_time()).map_err(|(n, msg)| match n {
+        0 => format!("Error in session file {}. ({})", path.display(), msg),
+        n => format!("Error in session file {}:{}. ({})", path.display(), n, msg),
+    })
+}
+
+// Parse state of timers at UNIX time "now". Returns the line number and a
+// message on error, line 0 if the session as a whole is broken.
+pub fn parse(content: &str, now: u64) -> Result<Vec<Setup>, (usize, String)> {
+    let mut setups: Vec<Setup> = Vec::new();
+
+    for (n, line) in content.lines().enumerate() {
+        let error = |msg: &str| (n + 1, msg.to_string());
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        if key == "timer" {
+            setups.push(Setup::new(Some(value.to_string())));
+            continue;
+        }
+        let setup = setups
+            .last_mut()
+            .ok_or_else(|| error("Entry outside of timer."))?;
+
+        match key {
+            "start" => {
+                let start: u64 = value.parse().map_err(|_| error("Invalid start time."))?;
+                setup.total = u32::try_from(now.saturating_sub(start)).unwrap_or(u32::MAX);
+            }
+            "paused" => {
+                let (days, secs) = value
+                    .split_once(' ')
+                    .and_then(|(d, s)| Some((d.parse::<u32>().ok()?, s.parse::<u32>().ok()?)))
+                    .ok_or_else(|| error("Invalid clock state."))?;
+                setup.total = days.saturating_mul(24 * 60 * 60).saturating_add(secs);
+                setup.paused = true;
+            }
+            "target" => {
+                setup.target = Some(value.parse().map_err(|_| error("Invalid target."))?);
+            }
+            "countdown" => setup.count_down = true,
+            "alarm" => setup.alarm_roster.restore(value).map_err(error)?,
+            "lap" => setup.alarm_roster.laps.restore(value).map_err(error)?,
+            "sequence" => setup.sequence = Some(Sequence::restore(value).map_err(error)?),
+            "step" | "completed" => {
+                let sequence = setup
+                    .sequence
+                    .as_mut()
+                    .ok_or_else(|| error("Entry outside of sequence."))?;
+                match key {
+                    "step" => sequence.restore_step(value),
+                    _ => sequence.restore_completed(value),
+                }
+                .map_err(error)?;
+            }
+            _ => return Err(error("Unknown entry.")),
+        }
+    }
+
+    if setups.is_empty() {
+        return Err((0, String::from("No timers.")));
+    }
+    if setups.iter().filter_map(|s| s.sequence.as_ref()).any(|s| s.len() == 0) {
+        return Err((0, String::from("Sequence without steps.")));
+    }
+    Ok(setups)
+}
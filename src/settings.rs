@@ -0,0 +1,185 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// The config file provides defaults for the command line options. One entry
+// per line, options given on the command line take precedence:
+//
+//   font NAME             Built-in font or file in the "fonts" directory.
+//   font-file PATH        Font to load, see clock::font.
+//   figlet-font PATH      FIGlet font to load.
+//   quit                  Quit program after last alarm.
+//   tenths                Display tenths of a second.
+//   scale                 Scale the clock to fill the window.
+//   title                 Show the clock in the window title.
+//   notify auto|bell|osc9|osc777|osc99
+//                         Notification sent by the terminal on alarm.
+//   exec COMMAND          Execute COMMAND on alarm. May be given repeatedly.
+//   nag TIME
+//   snooze TIME
+//   colors COLOR...       Colors of alarms and timers by name ("red",
+//                         "light-blue", ...), ANSI value or #RRGGBB.
+//   alarm ALARM[/LABEL]   Alarm to set if none are given on the command line.
+//   bind KEY ACTION       Bind KEY to ACTION in addition to its default key.
+
+use crate::alarm::parse_time;
+use crate::clock::{figlet, font};
+use crate::cradle::Cradle;
+use crate::{notify, palette, Config};
+use std::io;
+use std::path::PathBuf;
+use std::{env, fs};
+use termion::event::Key;
+
+// Directory holding the config file. Follows the XDG base directory
+// specification.
+pub fn dir() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(dir.join(env!("CARGO_PKG_NAME")))
+}
+
+// Read config file into "config". A missing file is not an error.
+pub fn load(config: &mut Config) -> Result<(), String> {
+    let path = match dir() {
+        Some(dir) => dir.join("config"),
+        None => return Ok(()),
+    };
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => {
+            return Err(format!("Error reading config file {}. ({})", path.display(), e))
+        }
+    };
+    parse(config, &content).map_err(|(n, msg)| {
+        format!("Error in config file {}:{}. ({})", path.display(), n, msg)
+    })
+}
+
+// Apply config file content. Returns the line number and a message on error.
+pub fn parse(config: &mut Config, content: &str) -> Result<(), (usize, String)> {
+    for (n, line) in content.lines().enumerate() {
+        let error = |msg: &str| (n + 1, msg.to_string());
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(char::is_whitespace) {
+            Some((key, value)) => (key, value.trim()),
+            None => (line, ""),
+        };
+        if value.is_empty() && !["quit", "tenths", "scale", "title"].contains(&key) {
+            return Err(error("Missing parameter."));
+        }
+
+        match key {
+            "font" => config.font = config.fonts.find(value).map_err(|e| error(&e))?,
+            "font-file" => config.font = font::load(value).map_err(|e| error(&e))?,
+            "figlet-font" => config.font = figlet::load(value).map_err(|e| error(&e))?,
+            "quit" => config.quit = true,
+            "tenths" => config.tenths = true,
+            "scale" => config.scale = true,
+            "title" => config.title = true,
+            "notify" => {
+                config.notify = match notify::Backend::parse(value) {
+                    Some(backend) => backend,
+                    None => return Err(error("Unknown notification backend.")),
+                }
+            }
+            "exec" => config.commands.add(Cradle::parse(value.to_string())),
+            "nag" | "snooze" => {
+                let time = parse_time(value).map_err(error)?;
+                if time == 0 {
+                    return Err(error("Time evaluates to zero."));
+                }
+                if key == "nag" {
+                    config.nag = time;
+                } else {
+                    config.snooze = time;
+                }
+            }
+            "colors" => {
+                for name in value.split_whitespace() {
+                    config.palette.push(palette::parse(name).map_err(error)?);
+                }
+            }
+            "alarm" => {
+                config.alarms.add(value).map_err(error)?;
+            }
+            "bind" => {
+                let (key, action) = value
+                    .split_once(char::is_whitespace)
+                    .ok_or_else(|| error("Expected key and action."))?;
+                let key = parse_key(key).ok_or_else(|| error("Unknown key."))?;
+                let action = action_key(action.trim()).ok_or_else(|| error("Unknown action."))?;
+                config.keys.insert(key, action);
+            }
+            _ => return Err(error("Unknown entry.")),
+        }
+    }
+    Ok(())
+}
+
+// Parse key name. Single chars stand for themselves, ^X for [CTRL]+[X].
+fn parse_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "space" => Key::Char(' '),
+        "tab" => Key::Char('\t'),
+        "backtab" => Key::BackTab,
+        "up" => Key::Up,
+        "down" => Key::Down,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next()?, chars.next(), chars.next()) {
+                (c, None, None) => Key::Char(c),
+                ('^', Some(c), None) => Key::Ctrl(c.to_ascii_lowercase()),
+                _ => return None,
+            }
+        }
+    };
+    Some(key)
+}
+
+// Default key triggering the given action.
+fn action_key(action: &str) -> Option<Key> {
+    let key = match action {
+        "quit" => Key::Char('q'),
+        "pause" => Key::Char(' '),
+        "reset" => Key::Char('r'),
+        "countdown" => Key::Char('t'),
+        "acknowledge" => Key::Char('a'),
+        "snooze" => Key::Char('s'),
+        "clear" => Key::Char('c'),
+        "select-next" => Key::Char('j'),
+        "select-previous" => Key::Char('k'),
+        "edit" => Key::Char('e'),
+        "label" => Key::Char('l'),
+        "delete" => Key::Char('d'),
+        "next-timer" => Key::Char('\t'),
+        "previous-timer" => Key::BackTab,
+        "lap" => Key::Char('\n'),
+        "export-laps" => Key::Char('w'),
+        "next-font" => Key::Char('f'),
+        "redraw" => Key::Ctrl('r'),
+        _ => return None,
+    };
+    Some(key)
+}
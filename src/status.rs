@@ -0,0 +1,260 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Headless mode printing a status line once every second, for use in status
+// bars.
+
+use crate::timer::Timer;
+use crate::utils::{format_duration, json_escape};
+use crate::{control, Config, Event};
+use signal_hook::consts::signal::*;
+use signal_hook::iterator::Signals;
+use std::io::Write;
+use std::sync::mpsc;
+use std::{thread, time};
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    // Plain text, one line per update.
+    Plain,
+    // i3bar JSON protocol.
+    I3bar,
+    // JSON for custom waybar modules.
+    Waybar,
+}
+
+impl Format {
+    pub fn parse(name: &str) -> Option<Format> {
+        match name {
+            "plain" => Some(Format::Plain),
+            "i3bar" => Some(Format::I3bar),
+            "waybar" => Some(Format::Waybar),
+            _ => None,
+        }
+    }
+}
+
+pub fn run(
+    mut config: Config,
+    mut timers: Vec<Timer>,
+    format: Format,
+    rx: mpsc::Receiver<Event>,
+) -> Result<(), std::io::Error> {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    let mut signals = Signals::new([SIGTERM, SIGINT, SIGUSR1, SIGUSR2])?;
+    let mut force_update = true;
+
+    if let Format::I3bar = format {
+        // Header and start of the endless array of status lines.
+        writeln!(stdout, "{{\"version\":1}}")?;
+        writeln!(stdout, "[")?;
+    }
+
+    'main: loop {
+        // Check on last spawned child process prior to processing the
+        // alarm rosters and possibly spawning a new set.
+        config.commands.tend();
+
+        let mut update = force_update;
+        let mut raised = false;
+        for timer in timers.iter_mut() {
+            if !(timer.tick()
+                || force_update
+                || timer.clock.paused && timer.alarm_roster.time_of_day_pending()
+                || timer.alarm_roster.until_time_of_day() == Some(time::Duration::ZERO))
+            {
+                continue;
+            }
+            update = true;
+
+            let Timer {
+                clock,
+                alarm_roster,
+                countdown,
+                layout,
+                ..
+            } = timer;
+            if let Some(alarm) = alarm_roster.check(clock, layout, countdown, &mut force_update) {
+                // Do not react to exceeded alarms if the clock is paused,
+                // unless they are set to a time of day.
+                if !clock.paused || alarm.is_time_of_day() {
+                    raised = true;
+                    config.commands.run_all(alarm.nominal_time(), &alarm.label);
+                }
+            }
+
+            // Notify again about alarms not acknowledged yet.
+            if config.nag > 0 && !clock.paused {
+                if let Some(alarm) = alarm_roster.nag(config.nag) {
+                    config.commands.run_all(alarm.nominal_time(), &alarm.label);
+                }
+            }
+
+            // Begin next step of the sequence.
+            if timer.advance() {
+                force_update = true;
+            }
+        }
+        crate::write_events(&mut config, &mut timers);
+
+        if update {
+            match format {
+                Format::Plain => writeln!(stdout, "{}", text(&timers))?,
+                Format::I3bar => writeln!(
+                    stdout,
+                    "[{{\"name\":\"{}\",\"full_text\":\"{}\",\"urgent\":{}}}],",
+                    env!("CARGO_PKG_NAME"),
+                    json_escape(&text(&timers)),
+                    timers.iter().any(|t| t.alarm_roster.pending()),
+                )?,
+                Format::Waybar => writeln!(
+                    stdout,
+                    "{{\"text\":\"{}\",\"tooltip\":\"{}\",\"class\":\"{}\"}}",
+                    json_escape(&text(&timers)),
+                    json_escape(&tooltip(&timers)),
+                    class(&timers),
+                )?,
+            }
+            stdout.flush()?;
+            force_update = false;
+        }
+
+        // Quit if configured and the last alarm has been raised.
+        if raised && config.quit && timers.iter().all(|timer| timer.alarm_roster.idle()) {
+            break;
+        }
+
+        // Process commands received on the control socket. The first timer
+        // receives commands not addressed to a specific one.
+        match rx.recv_timeout(crate::timeout(&timers)) {
+            Ok(Event::Control(request)) => {
+                let reply = match &request.timer {
+                    Some(name) => timers
+                        .iter_mut()
+                        .find(|timer| &timer.name == name)
+                        .ok_or("No such timer."),
+                    None => Ok(&mut timers[0]),
+                }
+                .and_then(|timer| crate::handle_command(timer, &request.command, &config.recipes));
+                // Ignore clients not waiting for the reply.
+                let _ = request.reply.send(reply.map_err(String::from));
+                force_update = true;
+            }
+            Ok(Event::Key(_)) | Err(mpsc::RecvTimeoutError::Timeout) => (),
+            // No control socket.
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                thread::sleep(time::Duration::from_millis(250))
+            }
+        }
+
+        // Process received signals.
+        for signal in signals.pending() {
+            match signal {
+                // Exit main loop on SIGTERM and SIGINT.
+                SIGTERM | SIGINT => break 'main,
+                // Reset first clock on SIGUSR1. Like commands on the
+                // control socket, signals address the first timer.
+                SIGUSR1 => {
+                    timers[0].reset();
+                    force_update = true;
+                }
+                // (Un-)Pause first clock on SIGUSR2.
+                SIGUSR2 => {
+                    timers[0].toggle();
+                    force_update = true;
+                }
+                // We didn't register anything else.
+                _ => unreachable!(),
+            }
+        }
+    }
+    crate::write_events(&mut config, &mut timers);
+
+    if let Some(path) = &config.socket {
+        control::remove(path);
+    }
+
+    crate::export_laps(&config, &timers);
+
+    // Statistics of sequences. Standard output belongs to the status bar.
+    for timer in timers.iter() {
+        if let Some(summary) = timer.sequence.as_ref().and_then(|s| s.summary()) {
+            eprintln!("{}: {}", timer.name, summary);
+        }
+    }
+    Ok(())
+}
+
+// Clock of every timer followed by the next alarm and the time remaining
+// until it goes off.
+pub fn text(timers: &[Timer]) -> String {
+    let mut parts = Vec::new();
+    for timer in timers {
+        let mut part = String::new();
+        if timers.len() > 1 {
+            part.push_str(&timer.name);
+            part.push(' ');
+        }
+        let (value, overtime) = timer.clock.value();
+        if timer.clock.count_down && !overtime {
+            part.push('-');
+        }
+        part.push_str(&format_duration(value));
+        if let Some(alarm) = timer.alarm_roster.next() {
+            part.push_str(&format!(
+                " {} {}",
+                alarm.label,
+                format_duration(timer.countdown.value)
+            ));
+        }
+        parts.push(part);
+    }
+    parts.join(" | ")
+}
+
+// All alarms yet to go off.
+fn tooltip(timers: &[Timer]) -> String {
+    let mut lines = Vec::new();
+    for timer in timers {
+        let total = timer.clock.total();
+        for alarm in timer.alarm_roster.upcoming() {
+            let mut line = String::new();
+            if timers.len() > 1 {
+                line.push_str(&format!("{}: ", timer.name));
+            }
+            line.push_str(&format!(
+                "{} in {}",
+                alarm.label,
+                format_duration(alarm.time.saturating_sub(total))
+            ));
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+// CSS class for waybar.
+fn class(timers: &[Timer]) -> &'static str {
+    if timers.iter().any(|t| t.alarm_roster.pending()) {
+        "pending"
+    } else if timers.iter().all(|t| t.clock.paused) {
+        "paused"
+    } else {
+        "running"
+    }
+}
@@ -15,16 +15,63 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
-use crate::clock::{Clock, font};
+use crate::clock::{self, Clock, font};
 use crate::layout::Layout;
-use crate::{Config, Cradle};
+use crate::recipe::Recipes;
+use crate::timer::{Setup, Timer};
+use crate::title;
+use crate::notify::{self, Backend};
+use crate::palette::Palette;
+use crate::alarm::Alarm;
+use crate::{AlarmRoster, Config, Cradle};
+use std::collections::HashMap;
+use std::time::Duration;
 
 fn default_config() -> Config {
     Config {
-        fancy: false,
         quit: false,
         commands: Cradle::new(),
         font: &font::NORMAL,
+        fonts: font::Fonts::default(),
+        timers: vec![Setup::new(None)],
+        resume: false,
+        socket: None,
+        status: None,
+        events: None,
+        tenths: false,
+        scale: false,
+        title: false,
+        notify: notify::Backend::Bell,
+        laps: None,
+        nag: 0,
+        snooze: 300,
+        palette: Palette::new(),
+        alarms: AlarmRoster::new(),
+        keys: HashMap::new(),
+        recipes: Recipes::new(),
+    }
+}
+
+// Timer laid out in a 80x24 window.
+fn test_timer(config: &Config) -> Timer {
+    let mut timer = Timer::new(config, 0, Setup::new(None));
+    timer.layout.test_update(&timer.clock, 80, 24, timer.alarm_roster.width());
+    timer
+}
+
+// Set the clock of "timer" to "secs" and check its alarms.
+fn check_at(timer: &mut Timer, secs: u64) -> Option<Alarm> {
+    let Timer { clock, alarm_roster, countdown, layout, .. } = timer;
+    clock.elapsed = Duration::from_secs(secs);
+    alarm_roster.check(clock, layout, countdown, &mut false)
+}
+
+// Environment variables for functions looking them up.
+fn fake_env(vars: &'static [(&'static str, &'static str)]) -> impl Fn(&str) -> Option<String> {
+    move |name| {
+        vars.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| value.to_string())
     }
 }
 
@@ -44,7 +91,7 @@ fn layout_computation() {
         }
     }
     // Three segment display.
-    clock.elapsed = 3600;
+    clock.elapsed = Duration::from_secs(3600);
     for roster_width in &[0, 10, 20, 30, 40] {
         for width in 0..256 {
             for height in 0..128 {
@@ -53,3 +100,961 @@ fn layout_computation() {
         }
     }
 }
+
+// Test splitting the screen between multiple timers.
+#[test]
+fn split_layout() {
+    let config = default_config();
+    let clock = Clock::new(&config);
+
+    for slots in 2..5 {
+        let mut layouts: Vec<Layout> = (0..slots)
+            .map(|slot| {
+                let mut layout = Layout::new();
+                layout.set_slot(slot, slots);
+                layout
+            })
+            .collect();
+        for width in 0..128 {
+            for height in 0..64 {
+                for layout in layouts.iter_mut() {
+                    layout.test_update(&clock, width, height, 10);
+                }
+            }
+        }
+        // Regions must not overlap.
+        for pair in layouts.windows(2) {
+            assert!(pair[0].roster.line + pair[0].roster_height < pair[1].header.line);
+            assert!(pair[0].clock_sec.line + pair[0].font.height <= pair[1].header.line);
+        }
+    }
+
+    // Fall back to the small font if space is short.
+    let mut layout = Layout::new();
+    layout.set_slot(0, 2);
+    layout.test_update(&clock, 80, 20, 0);
+    assert_eq!(layout.font.height, font::NORMAL.height);
+    layout.set_slot(0, 3);
+    layout.test_update(&clock, 80, 20, 0);
+    assert_eq!(layout.font.height, font::SMALL.height);
+}
+
+// Test if tenths of a second fit next to the seconds.
+#[test]
+fn tenths_layout() {
+    let mut config = default_config();
+    config.tenths = true;
+    let mut clock = Clock::new(&config);
+    let mut layout = Layout::new();
+
+    for width in 0..256 {
+        for height in 0..128 {
+            layout.test_update(&clock, width, height, 0);
+        }
+    }
+    layout.test_update(&clock, 80, 24, 0);
+    assert!(layout.clock_tenth.col > layout.clock_sec.col + layout.font.width * 2);
+    assert!(layout.clock_tenth.col + layout.font.width <= 80);
+    // Roughly centered.
+    let left = layout.clock_min.col - 1;
+    let right = 80 - (layout.clock_tenth.col + layout.font.width - 1);
+    assert!(left.abs_diff(right) <= 2, "{} {}", left, right);
+
+    // Wake up ten times a second unless paused.
+    clock.elapsed = Duration::from_millis(1250);
+    assert_eq!(clock.tenth(), Some(2));
+    assert_eq!(clock.until_next(), Some(Duration::from_millis(50)));
+    clock.toggle();
+    assert_eq!(clock.until_next(), None);
+    // Pausing keeps the elapsed time.
+    assert_eq!(clock.precise(), Duration::from_millis(1250));
+    clock.tenths = false;
+    assert_eq!(clock.tenth(), None);
+}
+
+// Test if the countdown display switches segments at the right time.
+#[test]
+fn countdown_hour_barrier() {
+    let config = default_config();
+    let mut setup = Setup::new(None);
+    setup.target = Some(2 * 3600);
+    setup.count_down = true;
+    let Timer { mut clock, .. } = Timer::new(&config, 0, setup);
+
+    assert!(clock.shows_hours());
+    clock.elapsed = Duration::from_secs(3600);
+    assert!(!clock.hour_barrier());
+    clock.elapsed = Duration::from_secs(3601);
+    assert!(clock.hour_barrier());
+    assert!(!clock.shows_hours());
+    // Counting up again after the target was exceeded.
+    clock.elapsed = Duration::from_secs(3 * 3600);
+    assert!(clock.hour_barrier());
+    assert!(clock.shows_hours());
+    // Counting up as usual.
+    assert!(clock.toggle_direction());
+    assert!(!clock.hour_barrier());
+}
+
+// Test parsing of alarm times.
+#[test]
+fn alarm_time_parsing() {
+    let valid = [
+        ("90", 90),
+        ("1:30", 90),
+        ("1:00:00/Label", 3600),
+        ("5m", 300),
+        ("1h30m", 5400),
+        ("1h 30m 15s", 5415),
+        ("2.5m", 150),
+        (".5h", 1800),
+        ("3 min/Tea", 180),
+        ("2 Hours", 7200),
+        ("2d04:00:00", 2 * 86400 + 4 * 3600),
+        ("1d 0:30", 86400 + 30),
+        ("1d12h", 86400 + 12 * 3600),
+        ("3 days/Curing", 3 * 86400),
+    ];
+    for (input, time) in valid.iter() {
+        assert_eq!(AlarmRoster::new().add(input), Ok(*time), "input: {}", input);
+    }
+
+    let invalid = [
+        "1h30", "30m1h", "5m5m", "1.5:00", "1h:30", "0.1s", "5x", "h", "1..5m",
+        "99999999999h", "0m", "xd10:00", "1.5d10:00",
+    ];
+    for input in invalid.iter() {
+        assert!(AlarmRoster::new().add(input).is_err(), "input: {}", input);
+    }
+}
+
+// Test parsing of alarms set to a time of day.
+#[test]
+fn time_of_day_parsing() {
+    for input in ["@18:30", "@0:00/Midnight", "@23:59:59", "@7"].iter() {
+        let time = AlarmRoster::new().add(input);
+        assert!(
+            matches!(time, Ok(t) if t > 0 && t <= 24 * 60 * 60),
+            "input: {}",
+            input
+        );
+    }
+    for input in ["@24:00", "@12:60", "@1:2:3:4", "@5m", "@"].iter() {
+        assert!(AlarmRoster::new().add(input).is_err(), "input: {}", input);
+    }
+    let mut roster = AlarmRoster::new();
+    assert!(roster.add("@12:00/Lunch").is_ok());
+    assert!(roster.add("@12:00").is_err());
+}
+
+// Test if alarms beyond the first day are raised once and stay exceeded.
+#[test]
+fn multi_day_alarms() {
+    let config = default_config();
+    let mut timer = test_timer(&config);
+    timer.alarm_roster.add("10:00/Today").unwrap();
+    timer.alarm_roster.add("1d00:10:00/Tomorrow").unwrap();
+
+    let fired = check_at(&mut timer, 600);
+    assert_eq!(fired.map(|a| a.label).as_deref(), Some("Today"));
+    assert_eq!(timer.countdown.value, 86400);
+
+    // Next day.
+    timer.clock.days = 1;
+    assert!(check_at(&mut timer, 599).is_none());
+    let fired = check_at(&mut timer, 600);
+    assert_eq!(fired.map(|a| a.label).as_deref(), Some("Tomorrow"));
+    assert!(timer.alarm_roster.idle());
+}
+
+// Test scheduling of repeating alarms.
+#[test]
+fn repeating_alarms() {
+    let config = default_config();
+    let mut timer = test_timer(&config);
+    let roster = &mut timer.alarm_roster;
+    assert_eq!(roster.add("*10m x3/Stir"), Ok(600));
+    assert_eq!(roster.add("every 15m until 40m"), Ok(900));
+    for input in ["*0", "*10m x0", "*10m until 5m", "*10m xq", "*5d x"].iter() {
+        assert!(roster.add(input).is_err(), "input: {}", input);
+    }
+
+    // Both alarms go off at 30:00.
+    let raised: Vec<u64> = (0..=3600).filter(|time| check_at(&mut timer, *time).is_some()).collect();
+    assert_eq!(raised, vec![600, 900, 1200, 1800]);
+    assert!(timer.alarm_roster.idle());
+
+    // Travel back in time.
+    timer.clock.elapsed = Duration::from_secs(1000);
+    timer.alarm_roster.time_travel(&mut timer.clock);
+    assert!(!timer.alarm_roster.idle());
+    assert!(check_at(&mut timer, 1000).is_none());
+    assert_eq!(timer.countdown.value, 200);
+
+    // Editing keeps occurrences passed and pending state.
+    let mut timer = test_timer(&config);
+    timer.alarm_roster.add("*1m x5/Stir").unwrap();
+    assert!(check_at(&mut timer, 150).is_some());
+    let roster = &mut timer.alarm_roster;
+    assert!(roster.select(&timer.layout, true));
+    assert!(roster.edit("*1m x5/Stir hard").is_ok());
+    assert!(roster.pending());
+    assert_eq!(roster.selection().map(|a| a.time), Some(180));
+    assert!(roster.edit("*1m x2").is_ok());
+    assert!(roster.idle());
+}
+
+// Test snoozing and acknowledging alarms.
+#[test]
+fn snooze_alarms() {
+    let config = default_config();
+    let mut timer = test_timer(&config);
+    timer.alarm_roster.add("1:00/Tea").unwrap();
+    assert!(!timer.alarm_roster.snooze(&timer.clock, 300));
+    assert!(check_at(&mut timer, 60).is_some());
+    let roster = &mut timer.alarm_roster;
+    assert!(roster.pending());
+    assert!(roster.nag(0).is_some());
+
+    // Snooze adds a one-off copy.
+    assert!(roster.snooze(&timer.clock, 300));
+    assert!(!roster.pending());
+    assert!(!roster.idle());
+    let alarm = check_at(&mut timer, 360);
+    assert_eq!(alarm.map(|a| a.label).as_deref(), Some("Tea"));
+    let roster = &mut timer.alarm_roster;
+    assert!(roster.acknowledge());
+    assert!(!roster.pending());
+    assert!(roster.idle());
+
+    // Copies are dropped on reset.
+    roster.reset_all();
+    assert_eq!(roster.last_time(), Some(60));
+
+    // Copies of a selected alarm are not selected.
+    roster.remove("Tea").unwrap();
+    roster.add("*1m/Egg").unwrap();
+    assert!(check_at(&mut timer, 60).is_some());
+    let roster = &mut timer.alarm_roster;
+    assert!(roster.select(&timer.layout, true));
+    assert!(roster.snooze(&timer.clock, 30));
+    assert_eq!(roster.selection().map(|a| a.time), Some(120));
+}
+
+// Test if alarms survive a round trip through the session file.
+#[test]
+fn session_alarms() {
+    let config = default_config();
+    let mut timer = test_timer(&config);
+    for input in &["1:00/Tea", "*2m x3/Stir", "@12:00/Lunch", "1h"] {
+        timer.alarm_roster.add(input).unwrap();
+    }
+    check_at(&mut timer, 150);
+    assert!(timer.alarm_roster.snooze(&timer.clock, 300));
+
+    let mut saved = Vec::new();
+    timer.alarm_roster.save(&mut saved).unwrap();
+    let saved = String::from_utf8(saved).unwrap();
+    let mut restored = AlarmRoster::new();
+    for line in saved.lines() {
+        restored.restore(line.strip_prefix("alarm ").unwrap()).unwrap();
+    }
+    let mut again = Vec::new();
+    restored.save(&mut again).unwrap();
+    assert_eq!(saved, String::from_utf8(again).unwrap());
+    assert!(!restored.overdue(&timer.clock));
+
+    // Alarms passed while not running.
+    timer.clock.elapsed = Duration::from_secs(3600);
+    assert!(restored.overdue(&timer.clock));
+    assert!(restored.restore("- x 1:00").is_err());
+
+    // Restored alarms may share their time.
+    let mut restored = AlarmRoster::new();
+    assert!(restored.restore("z 300 05:00/Tea").is_ok());
+    assert!(restored.restore("- 300 05:00/Egg").is_ok());
+    assert!(restored.restore("- 300 05:00/Egg").is_ok());
+
+    // Sequences and laps are kept along with the alarms.
+    use crate::sequence::Sequence;
+    use crate::session;
+    let mut timer = test_timer(&config);
+    timer.sequence = Sequence::pomodoro("25m/5m/15m/2").ok();
+    for end in [1500, 1800, 3300, 4200].iter() {
+        assert!(timer.advance());
+        assert!(check_at(&mut timer, *end).is_some());
+    }
+    assert!(timer.advance());
+    timer.lap();
+    let mut saved = Vec::new();
+    session::write(&mut saved, &[timer], 100_000).unwrap();
+    let saved = String::from_utf8(saved).unwrap();
+    assert!(saved.contains("\nsequence arb 0 1\n"));
+    assert!(saved.contains("\ncompleted 2 3000 Work\n"));
+    let timers: Vec<Timer> = session::parse(&saved, 100_000)
+        .unwrap()
+        .into_iter()
+        .map(|setup| Timer::new(&config, 0, setup))
+        .collect();
+    let mut again = Vec::new();
+    session::write(&mut again, &timers, 100_000).unwrap();
+    assert_eq!(saved, String::from_utf8(again).unwrap());
+    assert_eq!(timers[0].sequence.as_ref().and_then(|s| s.round()), Some(2));
+    assert!(session::parse("timer Tea\nstep 60 - Boil\n", 0).is_err());
+    assert!(session::parse("timer Tea\nsequence a 0 0\n", 0).is_err());
+}
+
+// Test selecting, editing and deleting alarms.
+#[test]
+fn roster_selection() {
+    let config = default_config();
+    let clock = Clock::new(&config);
+    let mut layout = Layout::new();
+    let mut roster = AlarmRoster::new();
+    layout.test_update(&clock, 80, 24, roster.width());
+
+    for input in ["1:00/Tea", "5:00/Egg", "8:00/Pasta"].iter() {
+        roster.add(input).unwrap();
+    }
+    assert!(roster.edit("2:00").is_err());
+
+    // Select "Egg".
+    assert!(roster.select(&layout, true));
+    assert!(roster.select(&layout, true));
+    assert_eq!(roster.selected(), Some(1));
+    assert_eq!(roster.selection().map(|a| a.spec()).as_deref(), Some("05:00"));
+
+    // Move to the end of the list.
+    assert!(roster.edit("10m/Soft egg").is_ok());
+    assert_eq!(roster.selected(), Some(2));
+    assert!(roster.edit("1:00").is_err());
+    assert!(roster.relabel("Hard egg").is_ok());
+    assert_eq!(roster.selection().map(|a| a.label.as_str()), Some("Hard egg"));
+
+    // Deletion selects the previous alarm when removing the last one.
+    assert_eq!(roster.delete(&layout).map(|a| a.time), Some(600));
+    assert_eq!(roster.selected(), Some(1));
+    assert!(roster.unselect());
+    assert_eq!(roster.delete(&layout).map(|a| a.time), Some(480));
+    assert_eq!(roster.last_time(), Some(60));
+}
+
+// Test escaping of strings written as JSON.
+#[test]
+fn json_escaping() {
+    use crate::utils::json_escape;
+
+    assert_eq!(json_escape("Tea"), "Tea");
+    assert_eq!(json_escape("\"Tea\"\\"), "\\\"Tea\\\"\\\\");
+    assert_eq!(json_escape("Tea\nStir\t"), "Tea\\nStir\\u0009");
+}
+
+// Test parsing of the config file.
+#[test]
+fn config_file() {
+    use crate::consts::COLOR;
+    use crate::settings::parse;
+    use termion::color;
+    use termion::event::Key;
+
+    let mut config = default_config();
+    let content = "# Defaults\n\
+                   font plain\n\
+                   quit\n\
+                   snooze 2m\n\
+                   alarm 3:00/Tea\n\
+                   alarm 4m\n\
+                   \n\
+                   bind x quit\n\
+                   bind ^n next-timer\n\
+                   colors blue #ff0000\n";
+    assert!(parse(&mut config, content).is_ok());
+    assert!(config.quit);
+    assert_eq!(config.snooze, 120);
+    assert_eq!(config.alarms.last_time(), Some(240));
+    assert_eq!(config.keys.get(&Key::Char('x')), Some(&Key::Char('q')));
+    assert_eq!(config.keys.get(&Key::Ctrl('n')), Some(&Key::Char('\t')));
+    // Colors replace the defaults of this config only.
+    let fg = |palette: &Palette, index| color::Fg(palette.get(index)).to_string();
+    assert_eq!(fg(&config.palette, 2), color::Fg(color::AnsiValue(4)).to_string());
+    assert_eq!(fg(&config.palette, 1), color::Fg(color::Rgb(255, 0, 0)).to_string());
+    assert_eq!(fg(&default_config().palette, 0), color::Fg(COLOR[0]).to_string());
+
+    // Errors point at the offending line.
+    let error = |content| parse(&mut default_config(), content).unwrap_err();
+    assert_eq!(error("quit\nfont huge").0, 2);
+    assert_eq!(error("\n\nalarm").0, 3);
+    assert_eq!(error("nag 0").0, 1);
+    assert_eq!(error("quit\nbind x fly").0, 2);
+    assert_eq!(error("colors red #12345").0, 1);
+    assert_eq!(error("sing").0, 1);
+}
+
+// Test parsing and adding of recipes.
+#[test]
+fn recipes() {
+    let content = "# Recipes\n\
+                   pasta\n\
+                   \x20   8:00/al dente\n\
+                   \x20   10:00/soft\n\
+                   tea\n\
+                   \x20   3:00\n\
+                   soup\n\
+                   \x20   1h\n\
+                   \x20   1q\n";
+    let mut recipes = Recipes::parse(content).unwrap();
+    assert_eq!(recipes.get("pasta").map(|r| r.len()), Ok(2));
+    assert!(recipes.define("egg=5m/soft;every 1m x3").is_ok());
+    assert!(recipes.define("egg").is_err());
+    assert!(recipes.define("9=5m").is_err());
+    assert!(recipes.define("egg=").is_err());
+
+    let mut roster = AlarmRoster::new();
+    assert!(recipes.add(&mut roster, "@pasta").is_ok());
+    assert!(recipes.add(&mut roster, "@soup").is_err());
+    assert!(recipes.add(&mut roster, "@stew").is_err());
+    assert!(recipes.add(&mut roster, "@egg").is_ok());
+    assert_eq!(roster.last_time(), Some(600));
+    // Nothing is added if one of the alarms fails.
+    assert!(recipes.add(&mut roster, "3:00/Tea").is_ok());
+    assert!(recipes.add(&mut roster, "@tea").is_err());
+    assert_eq!(roster.upcoming().count(), 5);
+
+    // Errors point at the offending line.
+    let error = |content| Recipes::parse(content).err().map(|e| e.0);
+    assert_eq!(error("  5m"), Some(1));
+    assert_eq!(error("tea\n  3:00\ntea\n  4:00"), Some(3));
+    assert_eq!(error("tea\n\n1tea"), Some(3));
+    // Recipes holding duplicates fail when used.
+    let recipes = Recipes::parse("tea\n  3:00\n  3m").unwrap();
+    assert!(recipes.add(&mut AlarmRoster::new(), "@tea").is_err());
+}
+
+// Test the line protocol of the control socket.
+#[test]
+fn control_socket() {
+    use crate::{control, Event};
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::sync::mpsc;
+    use std::{env, process, thread};
+
+    let path = env::temp_dir().join(format!("kitchentimer-test-{}.sock", process::id()));
+    let (tx, rx) = mpsc::channel();
+    control::listen(&path, tx).unwrap();
+    // Echo commands and the timer addressed.
+    thread::spawn(move || {
+        for event in rx {
+            if let Event::Control(request) = event {
+                let reply = match (request.timer, request.command.as_str()) {
+                    (_, "fail") => Err(String::from("Failed.")),
+                    (Some(timer), command) => Ok(format!("{}: {}", timer, command)),
+                    (None, command) => Ok(command.to_string()),
+                };
+                let _ = request.reply.send(reply);
+            }
+        }
+    });
+
+    let send = |args: &[&str]| {
+        let socket = ["--socket", path.to_str().unwrap()];
+        control::send(socket.iter().chain(args).map(|arg| arg.to_string()))
+    };
+    assert_eq!(send(&["add", "5:00/Tea"]), Ok(String::from("add 5:00/Tea")));
+    assert_eq!(send(&["--timer", "Egg", "status"]), Ok(String::from("Egg: status")));
+    assert_eq!(send(&["fail"]), Err(String::from("Failed.")));
+    assert_eq!(send(&["--timer"]), Err(String::from("Missing parameter to \"--timer\".")));
+    assert_eq!(send(&[]), Err(String::from("Missing command.")));
+
+    // Blank lines are skipped, "timer" applies to the following commands.
+    let mut stream = UnixStream::connect(&path).unwrap();
+    write!(stream, "\n  status \ntimer Egg\nreset\n").unwrap();
+    let mut lines = BufReader::new(stream).lines().map(Result::unwrap);
+    assert_eq!(lines.next().as_deref(), Some("ok status"));
+    assert_eq!(lines.next().as_deref(), Some("ok"));
+    assert_eq!(lines.next().as_deref(), Some("ok Egg: reset"));
+
+    // A running instance keeps its socket.
+    let (tx, _rx) = mpsc::channel();
+    assert!(control::listen(&path, tx).is_err());
+    control::remove(&path);
+}
+
+// Test processing of commands received on the control socket.
+#[test]
+fn control_commands() {
+    use crate::handle_command;
+
+    let config = default_config();
+    let recipes = &config.recipes;
+    let mut timer = Timer::new(&config, 0, Setup::new(None));
+    let ok = Ok(String::new());
+
+    assert_eq!(handle_command(&mut timer, "add 5:00/Tea", recipes), ok);
+    assert!(handle_command(&mut timer, "add 5:00", recipes).is_err());
+    assert_eq!(
+        handle_command(&mut timer, "status", recipes).as_deref(),
+        Ok("running 0 300 Tea")
+    );
+
+    // Pausing twice keeps the clock paused.
+    assert_eq!(handle_command(&mut timer, "pause", recipes), ok);
+    assert_eq!(handle_command(&mut timer, "pause", recipes), ok);
+    assert!(timer.clock.paused);
+    assert_eq!(handle_command(&mut timer, "shift +1:30", recipes), ok);
+    assert_eq!(handle_command(&mut timer, "shift -30", recipes), ok);
+    assert!(handle_command(&mut timer, "shift -x", recipes).is_err());
+    assert_eq!(
+        handle_command(&mut timer, "status", recipes).as_deref(),
+        Ok("paused 60 240 Tea")
+    );
+    // Shifting back may take off several days.
+    assert_eq!(handle_command(&mut timer, "shift +5d", recipes), ok);
+    assert_eq!(handle_command(&mut timer, "shift -3d", recipes), ok);
+    assert_eq!((timer.clock.days, timer.clock.total()), (2, 2 * 24 * 3600 + 60));
+    assert_eq!(handle_command(&mut timer, "shift -3d", recipes), ok);
+    assert_eq!((timer.clock.days, timer.clock.total()), (0, 0));
+    assert_eq!(handle_command(&mut timer, "resume", recipes), ok);
+    assert_eq!(handle_command(&mut timer, "resume", recipes), ok);
+    assert!(!timer.clock.paused);
+
+    assert_eq!(handle_command(&mut timer, "reset", recipes), ok);
+    assert_eq!(timer.clock.total(), 0);
+    assert_eq!(handle_command(&mut timer, "remove Tea", recipes), ok);
+    assert_eq!(
+        handle_command(&mut timer, "remove Tea", recipes),
+        Err("No such alarm.")
+    );
+    assert_eq!(handle_command(&mut timer, "status", recipes).as_deref(), Ok("running 0"));
+    assert_eq!(handle_command(&mut timer, "dance", recipes), Err("Unknown command."));
+}
+
+// Test the event stream.
+#[test]
+fn event_stream() {
+    let config = default_config();
+    let mut setup = Setup::new(Some(String::from("Kitchen")));
+    // Added before there is a sink to write to.
+    setup.alarm_roster.add("1:00/Tea").unwrap();
+    let mut timer = Timer::new(&config, 0, setup);
+    timer.layout.test_update(&timer.clock, 80, 24, timer.alarm_roster.width());
+    timer.emit("started");
+    assert!(check_at(&mut timer, 60).is_some());
+
+    let mut sink = Some(Vec::new());
+    timer.alarm_roster.events.flush(&mut sink, Some(&timer.name));
+    let written = String::from_utf8(sink.unwrap()).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("{\"time\":"));
+    assert!(lines[0].ends_with(
+        "\"event\":\"alarm_added\",\"timer\":\"Kitchen\",\
+         \"label\":\"Tea\",\"spec\":\"01:00\",\"alarm_time\":60}"
+    ));
+    assert!(lines[1].ends_with(
+        "\"event\":\"started\",\"timer\":\"Kitchen\",\"elapsed\":0,\"paused\":false}"
+    ));
+    assert!(lines[2].ends_with(
+        "\"event\":\"alarm_fired\",\"timer\":\"Kitchen\",\
+         \"label\":\"Tea\",\"alarm_time\":60,\"elapsed\":60}"
+    ));
+
+    // Events are dropped without a sink.
+    timer.toggle();
+    timer.alarm_roster.events.flush(&mut None::<Vec<u8>>, None);
+    let mut sink = Some(Vec::new());
+    timer.alarm_roster.events.flush(&mut sink, None);
+    assert_eq!(sink, Some(Vec::new()));
+}
+
+// Test advancing through the steps of a sequence.
+#[test]
+fn sequence_steps() {
+    use crate::sequence::Sequence;
+
+    let config = default_config();
+    let mut timer = test_timer(&config);
+    assert!(Sequence::parse("", false).is_err());
+    assert!(Sequence::parse("10m;0", false).is_err());
+    timer.sequence = Sequence::parse("10m/Boil; 5m; 25m/Bake", false).ok();
+    assert!(timer.advance());
+    assert_eq!(timer.alarm_roster.last_time(), Some(600));
+    assert!(!timer.advance());
+
+    // Wait for acknowledgement before beginning the next step.
+    assert!(check_at(&mut timer, 610).is_some());
+    assert!(!timer.advance());
+    assert!(timer.alarm_roster.acknowledge());
+    assert!(timer.advance());
+    let step = timer.sequence.as_ref().and_then(|s| s.current());
+    assert_eq!(step.map(|(_, s)| s.label.as_str()), Some("Step 2"));
+    assert_eq!(timer.alarm_roster.last_time(), Some(910));
+
+    // Steps report their own duration.
+    let alarm = check_at(&mut timer, 910).unwrap();
+    assert_eq!((alarm.nominal_time(), alarm.label.as_str()), (300, "Step 2"));
+    timer.sequence.as_mut().unwrap().restart(&mut timer.alarm_roster);
+    assert!(timer.alarm_roster.is_empty());
+
+    // Automatic sequences move on without acknowledgement.
+    timer.sequence = Sequence::parse("1m;2m", true).ok();
+    timer.clock.elapsed = Duration::ZERO;
+    assert!(timer.advance());
+    assert!(check_at(&mut timer, 60).is_some());
+    assert!(timer.advance());
+    assert_eq!(timer.alarm_roster.last_time(), Some(180));
+    assert!(check_at(&mut timer, 180).is_some());
+    assert!(timer.advance());
+    assert!(timer.sequence.as_ref().and_then(|s| s.current()).is_none());
+    assert!(!timer.advance());
+
+    // Steps ending while paused still wait for acknowledgement.
+    timer.alarm_roster.remove_steps();
+    timer.sequence = Sequence::parse("1m;2m", false).ok();
+    timer.clock.elapsed = Duration::ZERO;
+    assert!(timer.advance());
+    timer.clock.toggle();
+    check_at(&mut timer, 60);
+    assert!(timer.alarm_roster.pending());
+    assert!(!timer.advance());
+
+    // Steps whose alarm was removed or moved to another time do not count
+    // as completed.
+    for edit in [false, true].iter() {
+        timer.alarm_roster.remove_steps();
+        timer.sequence = Sequence::parse("1m/Boil;2m", true).ok();
+        assert!(timer.advance());
+        if *edit {
+            assert!(timer.alarm_roster.select(&timer.layout, true));
+            timer.alarm_roster.edit("10m/Boil").unwrap();
+        } else {
+            assert!(timer.alarm_roster.remove("Boil").is_some());
+        }
+        assert!(timer.advance());
+        assert!(timer.sequence.as_ref().and_then(|s| s.summary()).is_none());
+    }
+}
+
+// Test pomodoro cycles and their statistics.
+#[test]
+fn pomodoro() {
+    use crate::sequence::Sequence;
+
+    let config = default_config();
+    let mut timer = test_timer(&config);
+    assert!(Sequence::pomodoro("25m/5m").is_err());
+    assert!(Sequence::pomodoro("25m/5m/15m/0").is_err());
+    let sequence = Sequence::pomodoro("25m/5m/15m/2").unwrap();
+    assert_eq!(sequence.len(), 4);
+    assert!(sequence.summary().is_none());
+    timer.sequence = Some(sequence);
+
+    // Run through two rounds.
+    let mut labels = Vec::new();
+    for _ in 0..8 {
+        assert!(timer.advance());
+        let (_, step) = timer.sequence.as_ref().and_then(|s| s.current()).unwrap();
+        labels.push(step.label.clone());
+        let end = timer.clock.total() as u64 + step.time as u64;
+        assert!(check_at(&mut timer, end).is_some());
+    }
+    assert_eq!(labels[..4], ["Work", "Short break", "Work", "Long break"]);
+    assert_eq!(timer.sequence.as_ref().and_then(|s| s.round()), Some(2));
+    assert!(timer.advance());
+    let sequence = timer.sequence.as_ref().unwrap();
+    assert_eq!(sequence.round(), Some(3));
+    assert_eq!(
+        sequence.summary().as_deref(),
+        Some("Work 4x (01:40:00), Short break 2x (10:00), Long break 2x (30:00), 2 completed rounds")
+    );
+}
+
+// Test recording and export of laps.
+#[test]
+fn laps() {
+    use crate::lap::Laps;
+
+    let mut laps = Laps::new();
+    assert_eq!(laps.width(), 0);
+    assert_eq!(laps.record(Duration::from_millis(61_250)), 1);
+    assert_eq!(laps.record(Duration::from_millis(90_005)), 2);
+    assert_eq!(laps.record(Duration::from_secs(24 * 60 * 60 + 90)), 3);
+    // " 3. 1d00:01:30.00 +23:59:59.99"
+    assert_eq!(laps.width(), 30);
+
+    let mut csv = Vec::new();
+    laps.write_csv(&mut csv, "Tea \"1\"").unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "\"Tea \"\"1\"\"\",1,61.250,61.250\n\
+         \"Tea \"\"1\"\"\",2,90.005,28.755\n\
+         \"Tea \"\"1\"\"\",3,86490.000,86399.995\n"
+    );
+}
+
+// Test picking and formatting terminal notifications.
+#[test]
+fn notifications() {
+    assert_eq!(notify::detect(fake_env(&[])), Backend::Bell);
+    assert_eq!(notify::detect(fake_env(&[("KITTY_WINDOW_ID", "1")])), Backend::Osc99);
+    assert_eq!(notify::detect(fake_env(&[("TERM_PROGRAM", "iTerm.app")])), Backend::Osc9);
+    assert_eq!(notify::detect(fake_env(&[("TERM", "foot")])), Backend::Osc777);
+    // Multiplexers get the bell.
+    assert_eq!(
+        notify::detect(fake_env(&[("TMUX", "/tmp/tmux"), ("KITTY_WINDOW_ID", "1")])),
+        Backend::Bell
+    );
+
+    assert_eq!(notify::sequence(Backend::Bell, 180, "Tea"), "\x07");
+    assert_eq!(
+        notify::sequence(Backend::Osc9, 180, "Tea"),
+        "\x1b]9;kitchentimer: Tea (03:00)\x07\x07"
+    );
+    assert_eq!(
+        notify::sequence(Backend::Osc777, 180, "\x1b]Tea"),
+        "\x1b]777;notify;kitchentimer;]Tea (03:00)\x07\x07"
+    );
+    assert!(notify::sequence(Backend::Osc99, 180, "").contains(";Alarm at 03:00\x1b\\"));
+    assert_eq!(Backend::parse("osc777"), Some(Backend::Osc777));
+    assert_eq!(Backend::parse("osc42"), None);
+}
+
+// Test the window title.
+#[test]
+fn window_title() {
+    let config = default_config();
+    let mut setup = Setup::new(None);
+    setup.alarm_roster.add("1:00/Tea").unwrap();
+    let mut timers = vec![Timer::new(&config, 0, setup)];
+    assert!(check_at(&mut timers[0], 30).is_none());
+    assert_eq!(title::text(&timers), "00:30 Tea 00:30");
+    assert!(check_at(&mut timers[0], 60).is_some());
+    assert_eq!(title::text(&timers), "(!) Tea");
+    timers[0].alarm_roster.acknowledge();
+    assert_eq!(title::text(&timers), "01:00");
+
+    let mut output = Vec::new();
+    title::set(&mut output, "Tea\x07").unwrap();
+    assert_eq!(output, b"\x1b]0;Tea\x07");
+}
+
+// Test loading fonts from files.
+#[test]
+fn font_files() {
+    let mut content = String::from("# Three by three.\n");
+    for digit in 0..10 {
+        content.push_str(&format!("{}\n    |{} █|\n    |███|\n    |█ █|\n", digit, digit));
+    }
+    let parsed = font::parse(&format!("{}:\n    |  |\n    |▪▪|\n    |  |\n", content)).unwrap();
+    assert_eq!((parsed.width, parsed.height, parsed.colon_width), (3, 3, 2));
+    assert_eq!(parsed.digits[7][0], "7 █");
+    assert_eq!(parsed.dots, ('▪', '▪'));
+
+    let error = |colon: &str| font::parse(&format!("{}{}", content, colon)).err();
+    // Missing colon.
+    assert_eq!(error("").map(|e| e.0), Some(41));
+    // Colon of wrong height.
+    assert!(error(":\n    |▪|\n").is_some());
+    // Rows of different width.
+    assert_eq!(error(":\n    |▪|\n    |▪▪|\n    |▪|\n").map(|e| e.0), Some(44));
+    // Wide characters count twice.
+    assert!(error(":\n    |▪|\n    |好|\n    |▪|\n").is_some());
+    assert!(font::parse("1\n    |█|\n").is_err());
+    assert!(font::parse("10\n    |█|\n").is_err());
+    assert!(font::parse("    |█|\n").is_err());
+
+    // Layout keeps the clock centered and within the window.
+    let mut config = default_config();
+    config.font = Box::leak(Box::new(parsed));
+    let mut clock = Clock::new(&config);
+    let mut layout = Layout::new();
+    for elapsed in [0, 3600].iter() {
+        clock.elapsed = Duration::from_secs(*elapsed);
+        for width in 0..128 {
+            for height in 0..64 {
+                layout.test_update(&clock, width, height, 0);
+            }
+        }
+    }
+    layout.test_update(&clock, 40, 10, 0);
+    assert_eq!(layout.clock_sec.col, layout.clock_colon0.col + 4);
+    assert_eq!(layout.clock_min.col + 9, layout.clock_colon0.col);
+}
+
+// Test picking fonts by name and falling back on missing glyphs.
+#[test]
+fn font_selection() {
+    let mut fonts = font::Fonts::default();
+    assert_eq!(fonts.find("plain").map(|f| f.height), Ok(font::PLAIN.height));
+    assert_eq!(fonts.find("chrome").map(|f| f.name), Ok("chrome"));
+    assert!(fonts.find("huge").is_err());
+    // Fonts loaded otherwise join the cycle after the built-in ones.
+    let mut custom = font::scale(&font::SMALL, 2);
+    custom.name = "custom";
+    let custom: &'static font::Font = Box::leak(Box::new(custom));
+    fonts.keep(custom);
+    assert_eq!(fonts.find("custom").map(|f| f.name), Ok("custom"));
+    let mut font: &font::Font = &font::NORMAL;
+    while font.name != "custom" {
+        font = fonts.next(font).0;
+    }
+    assert_eq!(fonts.next(custom).0.name, "normal");
+
+    let utf8 = &[("TERM", "xterm-256color"), ("LANG", "en_US.UTF-8")];
+    assert!(font::supported(&font::CHROME, fake_env(utf8)));
+    assert!(!font::supported(&font::CHROME, fake_env(&[("TERM", "linux")])));
+    assert!(!font::supported(&font::CHROME, fake_env(&[("LC_ALL", "C"), ("LANG", "en_US.UTF-8")])));
+    assert!(font::supported(&font::NORMAL, fake_env(&[("TERM", "linux")])));
+    // All built-in digits fit their font.
+    for font in font::BUILTIN.iter() {
+        for glyph in font.digits.iter() {
+            assert_eq!(glyph.len(), font.height as usize, "{}", font.name);
+            for row in glyph.iter() {
+                assert_eq!(row.chars().count(), font.width as usize, "{}", font.name);
+            }
+        }
+    }
+}
+
+// Test importing FIGlet fonts.
+#[test]
+fn figlet_fonts() {
+    let mut content = String::from("flf2a$ 4 3 8 -1 2\nSample font\nwith two comment lines\n");
+    for code in b' '..=b'~' {
+        let rows: Vec<String> = match code {
+            b'1' => vec![" /|".into(), "  |".into(), "  |".into()],
+            b'0'..=b'9' => vec![" __ ".into(), format!("|{}$|", code as char), "|__|".into()],
+            b':' => vec!["  ".into(), "o ".into(), "o ".into()],
+            _ => vec!["$".into(), "$".into(), "$".into()],
+        };
+        for row in rows {
+            content.push_str(&format!("{}@\n", row));
+        }
+        // Blank row for descenders.
+        content.push_str("@@\n");
+    }
+    let parsed = clock::figlet::parse(&content).unwrap();
+    assert_eq!((parsed.width, parsed.height, parsed.colon_width), (4, 3, 2));
+    // Hardblanks become spaces, narrow digits are centered.
+    assert_eq!(parsed.digits[7], &[" __ ", "|7 |", "|__|"]);
+    assert_eq!(parsed.digits[1], &[" /| ", "  | ", "  | "]);
+    assert_eq!(parsed.dots, ('o', 'o'));
+
+    assert!(clock::figlet::parse("flf2a$ 4 3 8 -1 2\n").is_err());
+    assert!(clock::figlet::parse("flf2 4 3 8 -1 0\n").is_err());
+    assert!(clock::figlet::parse(&content.replace("flf2a$ 4", "flf2a$ x")).is_err());
+    // Huge heights must not overflow.
+    let huge = content.replace("flf2a$ 4", "flf2a$ 4611686018427387904");
+    assert!(matches!(clock::figlet::parse(&huge), Err((1, _))));
+    // Only the doubled end mark is stripped from the last row.
+    let marked = clock::figlet::parse(&content.replace("@@\n", "@@@\n")).unwrap();
+    assert_eq!(marked.height, 4);
+    assert_eq!(marked.digits[7][3], "@   ");
+}
+
+// Test scaling fonts to fill the window.
+#[test]
+fn font_scaling() {
+    let scaled = font::scale(&font::NORMAL, 2);
+    assert_eq!((scaled.width, scaled.height, scaled.colon_width), (10, 10, 2));
+    assert_eq!(scaled.digits[0][0], "██████████");
+    assert_eq!(scaled.digits[0][1], "██      ██");
+    // The dot separating tenths grows like the dots of the colon.
+    assert_eq!(scaled.dot, Some(&["██"][..]));
+    assert_eq!(font::scale(&font::NORMAL, 4).dot, Some(&["████", "████"][..]));
+    assert_eq!(font::NORMAL.dot_width(), 1);
+    // Fonts with other characters repeat them.
+    let chrome = font::scale(&font::CHROME, 3);
+    assert_eq!(chrome.digits[0][9], "🮐🮐🮐         🮐🮐🮐");
+    assert_eq!(chrome.height, 15);
+
+    let mut config = default_config();
+    config.scale = true;
+    let mut clock = Clock::new(&config);
+    let mut layout = Layout::new();
+    for elapsed in [0, 3600].iter() {
+        clock.elapsed = Duration::from_secs(*elapsed);
+        for width in 0..256 {
+            for height in 0..128 {
+                layout.test_update(&clock, width, height, 10);
+            }
+        }
+    }
+
+    // Fill large windows, leaving room for the alarm roster.
+    clock.elapsed = Duration::ZERO;
+    layout.test_update(&clock, 200, 60, 20);
+    assert_eq!(layout.font.height, font::NORMAL.height * 8);
+    assert!(!layout.compact);
+    assert!(layout.clock_min.col > 20);
+    assert!(layout.clock_sec.col + layout.font.width * 2 <= 200);
+    assert!(layout.clock_sec.line + layout.font.height < 60);
+    // Scaled fonts are generated once.
+    let font = layout.font;
+    layout.test_update(&clock, 200, 60, 20);
+    assert!(std::ptr::eq(font, layout.font));
+    // Room for the enlarged dot and tenths.
+    clock.tenths = true;
+    layout.test_update(&clock, 200, 60, 20);
+    assert_eq!(layout.clock_tenth.col, layout.clock_dot.col + layout.font.dot_width() + 1);
+    assert!(layout.clock_tenth.col + layout.font.width <= 201);
+    clock.tenths = false;
+    // Drop to a single line of text if nothing fits.
+    layout.test_update(&clock, 30, 7, 10);
+    assert!(layout.compact);
+}
+
+// Test the fallback layouts of windows too small for the clock.
+#[test]
+fn tiny_windows() {
+    let config = default_config();
+    let clock = Clock::new(&config);
+    let mut layout = Layout::new();
+    for slots in 1..4 {
+        layout.set_slot(slots - 1, slots);
+        for width in 0..100 {
+            for height in 0..40 {
+                layout.test_update(&clock, width, height, 0);
+                if layout.too_small {
+                    continue;
+                }
+                // Elements stay inside the window.
+                assert!(layout.clock_sec.line >= 1 && layout.clock_sec.line <= height);
+                if layout.compact {
+                    assert!(layout.next_alarm.col <= width + 1);
+                } else {
+                    assert!(layout.clock_sec.col + layout.font.width * 2 <= width);
+                }
+            }
+        }
+    }
+
+    layout.set_slot(0, 1);
+    // Fall back to the smaller font, then to a single line of text.
+    layout.test_update(&clock, 40, 4, 0);
+    assert!(!layout.compact);
+    assert_eq!(layout.font.height, font::SMALL.height);
+    layout.test_update(&clock, 12, 1, 0);
+    assert!(layout.compact && !layout.too_small);
+    assert_eq!((layout.clock_sec.col, layout.clock_sec.line), (1, 1));
+    assert_eq!(layout.next_alarm.col, 7);
+    // Give notice only if not even that fits.
+    layout.test_update(&clock, 5, 1, 0);
+    assert!(layout.too_small);
+    assert_eq!(layout.notice().map(|pos| (pos.col, pos.line)), Some((1, 1)));
+    // Each slot gives notice in its own region, if it has one.
+    layout.set_slot(1, 2);
+    layout.test_update(&clock, 5, 12, 0);
+    assert!(layout.too_small);
+    assert_eq!(layout.notice().map(|pos| (pos.col, pos.line)), Some((1, 7)));
+    layout.test_update(&clock, 5, 3, 0);
+    assert!(layout.notice().is_none());
+    layout.set_slot(0, 1);
+    // Positions are restored when the window grows again.
+    layout.test_update(&clock, 80, 24, 0);
+    assert!(!layout.compact && !layout.too_small);
+    let mut fresh = Layout::new();
+    fresh.test_update(&clock, 80, 24, 0);
+    assert_eq!(layout.clock_sec.col, fresh.clock_sec.col);
+    assert_eq!(layout.clock_sec.line, fresh.clock_sec.line);
+}
@@ -0,0 +1,302 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+use crate::alarm::{AlarmRoster, Countdown};
+use crate::clock::Clock;
+use crate::events::Value;
+use crate::layout::Layout;
+use crate::palette::Palette;
+use crate::sequence::Sequence;
+use crate::utils::format_duration;
+use crate::Config;
+use std::io::Write;
+use std::time::Duration;
+use termion::raw::RawTerminal;
+use termion::{color, cursor, style};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// Timer as given on the command line or restored from the session file.
+pub struct Setup {
+    pub name: Option<String>,
+    pub alarm_roster: AlarmRoster,
+    // Countdown target.
+    pub target: Option<u32>,
+    pub count_down: bool,
+    // Clock state in seconds.
+    pub total: u32,
+    pub paused: bool,
+    pub sequence: Option<Sequence>,
+}
+
+impl Setup {
+    pub fn new(name: Option<String>) -> Setup {
+        Setup {
+            name,
+            alarm_roster: AlarmRoster::new(),
+            target: None,
+            count_down: false,
+            total: 0,
+            paused: false,
+            sequence: None,
+        }
+    }
+}
+
+// Clock with its own alarm roster and its own part of the screen.
+pub struct Timer {
+    pub name: String,
+    pub clock: Clock,
+    pub alarm_roster: AlarmRoster,
+    pub countdown: Countdown,
+    pub layout: Layout,
+    // Countdown target given on the command line.
+    pub target: Option<u32>,
+    pub sequence: Option<Sequence>,
+    color_index: usize,
+}
+
+impl Timer {
+    pub fn new(config: &Config, index: usize, setup: Setup) -> Timer {
+        let mut clock = Clock::new(config);
+        // Count down to the last alarm if no target was given.
+        clock.target = match setup.target {
+            None if setup.count_down => setup.alarm_roster.last_time(),
+            target => target,
+        };
+        clock.count_down = setup.count_down && clock.target.is_some();
+        if setup.total > 0 || setup.paused {
+            clock.restore(setup.total, setup.paused);
+        }
+        // Color of a step resumed from the session file.
+        if let Some((_, step)) = setup.sequence.as_ref().and_then(|s| s.current()) {
+            clock.step_color = step.color;
+        }
+        let mut layout = Layout::new();
+        // Initialise roster_width.
+        layout.set_roster_width(setup.alarm_roster.width());
+
+        Timer {
+            name: setup
+                .name
+                .unwrap_or_else(|| format!("Timer {}", index + 1)),
+            clock,
+            alarm_roster: setup.alarm_roster,
+            countdown: Countdown::new(),
+            layout,
+            target: setup.target,
+            sequence: setup.sequence,
+            color_index: index,
+        }
+    }
+
+    // Update elapsed time. Returns true if the whole seconds changed.
+    pub fn tick(&mut self) -> bool {
+        let total = self.clock.total();
+        // Advance one day after 24 hours.
+        if self.clock.sync() {
+            self.layout.schedule_recalc();
+        }
+        self.clock.total() != total
+    }
+
+    // Time left until the clock display changes or an alarm set to a time
+    // of day is due.
+    pub fn until_next(&self) -> Option<Duration> {
+        match (self.clock.until_next(), self.alarm_roster.until_time_of_day()) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+
+    // (Un-)Pause clock.
+    pub fn toggle(&mut self) {
+        // Pause at the current time.
+        self.tick();
+        self.clock.toggle();
+        self.emit(if self.clock.paused { "paused" } else { "resumed" });
+    }
+
+    // Reset clock and alarms.
+    pub fn reset(&mut self) {
+        self.clock.reset();
+        self.alarm_roster.reset_all();
+        if let Some(sequence) = &mut self.sequence {
+            sequence.restart(&mut self.alarm_roster);
+        }
+        self.layout.schedule_recalc();
+        self.emit("reset");
+    }
+
+    // Set clock forward or back by "secs" seconds.
+    pub fn shift(&mut self, secs: i32) {
+        self.clock.shift(secs);
+        if secs.is_negative() {
+            self.alarm_roster.time_travel(&mut self.clock);
+        }
+        // We would very likely not detect us passing the hour barrier and
+        // would panic when trying to draw hours without position if we do
+        // not schedule a recalculation here.
+        self.layout.schedule_recalc();
+        self.alarm_roster.events.push(
+            "shifted",
+            &[
+                ("by", Value::Num(secs as i64)),
+                ("elapsed", Value::Num(self.clock.total() as i64)),
+            ],
+        );
+    }
+
+    // Begin next step of the sequence if the current one is done. Returns
+    // true on changes.
+    pub fn advance(&mut self) -> bool {
+        let sequence = match &mut self.sequence {
+            Some(sequence) => sequence,
+            None => return false,
+        };
+        if !sequence.advance(&mut self.alarm_roster, &self.clock) {
+            return false;
+        }
+        if let Some((index, step)) = sequence.current() {
+            if step.color.is_some() {
+                self.clock.color_index = None;
+                self.clock.step_color = step.color;
+            }
+            self.alarm_roster.events.push(
+                "step_started",
+                &[
+                    ("step", Value::Num(index as i64 + 1)),
+                    ("label", Value::Str(&step.label)),
+                ],
+            );
+        }
+        self.layout.set_roster_width(self.alarm_roster.width());
+        self.countdown.reset();
+        true
+    }
+
+    // Record lap. Returns the lap number.
+    pub fn lap(&mut self) -> usize {
+        self.tick();
+        let time = self.clock.precise();
+        let number = self.alarm_roster.laps.record(time);
+        self.layout.set_roster_width(self.alarm_roster.width());
+        self.alarm_roster.events.push(
+            "lap",
+            &[
+                ("lap", Value::Num(number as i64)),
+                ("elapsed_ms", Value::Num(time.as_millis() as i64)),
+            ],
+        );
+        number
+    }
+
+    // Queue event concerning this timer.
+    pub fn emit(&mut self, event: &'static str) {
+        self.alarm_roster.events.push(
+            event,
+            &[
+                ("elapsed", Value::Num(self.clock.total() as i64)),
+                ("paused", Value::Bool(self.clock.paused)),
+            ],
+        );
+    }
+
+    // Draw name of the timer. Highlight it if the timer has focus.
+    pub fn draw_header<W: Write>(
+        &self,
+        stdout: &mut RawTerminal<W>,
+        focused: bool,
+        palette: &Palette,
+    ) -> Result<(), std::io::Error> {
+        write!(
+            stdout,
+            "{}{}{}",
+            cursor::Goto(self.layout.header.col, self.layout.header.line),
+            color::Fg(palette.get(self.color_index)),
+            style::Bold,
+        )?;
+        if focused {
+            write!(stdout, "{}", style::Invert)?;
+        }
+        write!(
+            stdout,
+            " {} {}{}",
+            self.name,
+            style::Reset,
+            color::Fg(color::Reset),
+        )?;
+        if self.clock.paused {
+            write!(stdout, " {}[paused]{}", style::Faint, style::NoFaint)?;
+        }
+        Ok(())
+    }
+
+    // Draw current step of the sequence above the clock.
+    pub fn draw_step<W: Write>(&self, stdout: &mut RawTerminal<W>) -> Result<(), std::io::Error> {
+        let sequence = match &self.sequence {
+            Some(sequence) => sequence,
+            None => return Ok(()),
+        };
+        let mut text = match sequence.current() {
+            Some((index, step)) => format!("{}/{} {}", index + 1, sequence.len(), step.label),
+            None => String::from("Done"),
+        };
+        if let Some(round) = sequence.round() {
+            text.push_str(&format!(" (round {})", round));
+        }
+        let width = UnicodeWidthStr::width(text.as_str()) as u16;
+        let pos = &self.layout.step;
+        write!(
+            stdout,
+            "{}{}{}{}",
+            cursor::Goto(pos.col.saturating_sub(width / 2).max(1), pos.line),
+            style::Bold,
+            text,
+            style::Reset,
+        )
+    }
+
+    // Draw next alarm and the countdown to it right of the compact clock,
+    // cut off at the window border.
+    pub fn draw_next_alarm<W: Write>(&self, stdout: &mut RawTerminal<W>) -> Result<(), std::io::Error> {
+        let pos = &self.layout.next_alarm;
+        let room = (self.layout.width + 1).saturating_sub(pos.col) as usize;
+        let text = match self.alarm_roster.next() {
+            Some(alarm) if self.countdown.value > 0 => {
+                format!(" {} (-{})", alarm.label, format_duration(self.countdown.value))
+            }
+            _ => String::new(),
+        };
+        let mut width = 0;
+        let text: String = text
+            .chars()
+            .take_while(|c| {
+                width += c.width().unwrap_or(0);
+                width <= room
+            })
+            .collect();
+        // Overwrite leftovers of longer values.
+        write!(
+            stdout,
+            "{}{}{:padding$}",
+            cursor::Goto(pos.col, pos.line),
+            text,
+            "",
+            padding = room.saturating_sub(text.width()),
+        )
+    }
+}
@@ -0,0 +1,74 @@
+// Copyright 2021, Shy.
+//
+// This file is part of Kitchentimer.
+//
+// Kitchentimer is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Kitchentimer is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
+
+// Window and tab title showing the clock, so it can be watched from a
+// background tab.
+
+use crate::status;
+use crate::timer::Timer;
+use std::io::Write;
+
+// Saves the current title on the xterm title stack and restores it when
+// dropped, so the title is restored on every way out of the main loop.
+pub struct Title;
+
+impl Title {
+    pub fn push<W: Write>(stdout: &mut W) -> Result<Title, std::io::Error> {
+        write!(stdout, "\x1b[22;0t")?;
+        Ok(Title)
+    }
+}
+
+impl Drop for Title {
+    fn drop(&mut self) {
+        let mut stdout = std::io::stdout();
+        // Nothing left to do about errors at this point.
+        let _ = write!(stdout, "\x1b[23;0t").and_then(|_| stdout.flush());
+    }
+}
+
+// Set window and icon title.
+pub fn set<W: Write>(stdout: &mut W, title: &str) -> Result<(), std::io::Error> {
+    // Control characters would end the sequence prematurely.
+    let title: String = title.chars().filter(|c| !c.is_control()).collect();
+    write!(stdout, "\x1b]0;{}\x07", title)
+}
+
+// Clock and next alarm of every timer, or an attention marker with the
+// labels of alarms not acknowledged yet.
+pub fn text(timers: &[Timer]) -> String {
+    let mut labels = Vec::new();
+    for timer in timers {
+        for alarm in timer.alarm_roster.pending_alarms() {
+            let label = if alarm.label.is_empty() {
+                "Alarm"
+            } else {
+                &alarm.label
+            };
+            if timers.len() > 1 {
+                labels.push(format!("{}: {}", timer.name, label));
+            } else {
+                labels.push(label.to_string());
+            }
+        }
+    }
+    if labels.is_empty() {
+        status::text(timers)
+    } else {
+        format!("(!) {}", labels.join(", "))
+    }
+}
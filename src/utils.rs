@@ -15,14 +15,81 @@
 // You should have received a copy of the GNU General Public License
 // along with Kitchentimer.  If not, see <https://www.gnu.org/licenses/>.
 
+use std::convert::TryFrom;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use unicode_segmentation::UnicodeSegmentation;
 
 pub fn grapheme_truncate(input: &mut String, limit: usize, ellipse: char) {
-    match UnicodeSegmentation::grapheme_indices(input.as_str(), true).nth(limit) {
-        Some((i, _)) => {
-            input.truncate(i);
-            input.push(ellipse);
+    if let Some((i, _)) = UnicodeSegmentation::grapheme_indices(input.as_str(), true).nth(limit) {
+        input.truncate(i);
+        input.push(ellipse);
+    }
+}
+
+// Return local time of day in seconds since midnight.
+pub fn local_time_of_day(time: SystemTime) -> u32 {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(duration) => duration.as_secs() as libc::time_t,
+        Err(_) => 0,
+    };
+    // Safe as localtime_r() only writes to the given struct.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&secs, &mut tm) }.is_null() {
+        // Fall back to UTC.
+        return (secs % (24 * 60 * 60)) as u32;
+    }
+    (tm.tm_hour * 3600 + tm.tm_min * 60 + tm.tm_sec) as u32
+}
+
+// Format time of day as HH:MM, adding seconds only if necessary.
+pub fn format_time_of_day(secs: u32) -> String {
+    if secs % 60 == 0 {
+        format!("{:02}:{:02}", secs / 3600, (secs / 60) % 60)
+    } else {
+        format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    }
+}
+
+// Format duration as (HH:)MM:SS, prefixed by days if necessary.
+pub fn format_duration(secs: u32) -> String {
+    let days = secs / (24 * 60 * 60);
+    let secs = secs % (24 * 60 * 60);
+
+    if days > 0 {
+        format!(
+            "{}d{:02}:{:02}:{:02}",
+            days,
+            secs / 3600,
+            (secs / 60) % 60,
+            secs % 60
+        )
+    } else if secs >= 3600 {
+        format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+// Format duration like "format_duration", adding hundredths of a second.
+pub fn format_precise(duration: Duration) -> String {
+    format!(
+        "{}.{:02}",
+        format_duration(u32::try_from(duration.as_secs()).unwrap_or(u32::MAX)),
+        duration.subsec_millis() / 10
+    )
+}
+
+// Escape string for use in JSON.
+pub fn json_escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
         }
-        None => (),
     }
+    output
 }